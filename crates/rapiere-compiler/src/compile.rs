@@ -0,0 +1,472 @@
+//! Compilation of parsed filters into programs of the filter's stack machine.
+//!
+//! Filters are normalized before being compiled. Their operands are then compiled as
+//! follows:
+//! - the left-hand side of a comparison, as well as the arguments of a function, is loaded
+//!   from the record's path when it is a member starting with a bare word or traversing
+//!   fields, e.g. `a` or `a.b`, or is a literal otherwise;
+//! - the right-hand side of a comparison is always a literal, bare words being enum values,
+//!   e.g. `ACTIVE` in `state = ACTIVE`, and strings holding RFC 3339 timestamps or durations
+//!   in seconds being timestamps and durations, e.g. `"2024-01-01T00:00:00Z"` or `"20s"`;
+//! - a composite argument distributes its comparison over its global restrictions, e.g.
+//!   `a = (1 OR 2)` being compiled as `a = 1 OR a = 2`;
+//! - a global restriction tests whether its operand is truthy.
+//!
+//! Paths are dot-joined, whether their fields hold dots or not.
+
+use crate::{
+    error::{Error, RegisterError},
+    filter::Filter,
+    instruction::{Address, Instruction},
+    normalize::Normalization,
+    optimize::Optimizer,
+    registers::{Index, Literal, Register},
+};
+use rapiere_parser::{
+    self as parser, Arg, Comparable, Comparator, Expression, Member, Span, Value,
+};
+use std::hash::Hash;
+
+#[derive(Clone, Debug, Default)]
+pub struct Compiler {
+    normalization: Normalization,
+    optimizer: Option<Optimizer>,
+}
+
+impl Compiler {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Normalizes filters with the given passes and limits before compiling them.
+    #[inline]
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Optimizes compiled filters, which are left as emitted otherwise.
+    #[inline]
+    pub fn with_optimizer(mut self, optimizer: Optimizer) -> Self {
+        self.optimizer = Some(optimizer);
+        self
+    }
+
+    pub fn compile(&self, filter: parser::Filter) -> Result<Filter, Error> {
+        // Checked as written, as normalization may fold the operands holding them away
+        if let Some(expression) = &filter.expression {
+            reject_nested_comparisons(expression, false)?;
+        }
+
+        let filter = self.normalization.normalize(filter)?;
+        let mut program = Program::default();
+
+        match &filter.expression {
+            Some(expression) => program.expression(expression, None)?,
+            // An empty filter matches every record
+            None => program.literal(Value::Boolean(true))?,
+        }
+
+        Ok(match &self.optimizer {
+            Some(optimizer) => optimizer.optimize(program.filter),
+            None => program.filter,
+        })
+    }
+}
+
+/// Compiles a filter with the default normalization passes and limits.
+#[inline(always)]
+pub fn compile(filter: parser::Filter) -> Result<Filter, Error> {
+    Compiler::new().compile(filter)
+}
+
+/// Left-hand side and comparator distributed over the global restrictions of a composite
+/// argument.
+type Subject<'f> = (&'f Comparable, Comparator);
+
+#[derive(Default)]
+struct Program {
+    filter: Filter,
+
+    /// Span of the restriction being compiled
+    span: Span,
+}
+
+impl Program {
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        subject: Option<Subject>,
+    ) -> Result<(), Error> {
+        match expression {
+            Expression::And(operands) | Expression::Sequence(operands) => {
+                self.operands(operands, subject, Instruction::JumpIfFalse)
+            }
+            Expression::Or(operands) => self.operands(operands, subject, Instruction::JumpIfTrue),
+            Expression::Not(operand) => {
+                self.expression(operand, subject)?;
+                self.emit(Instruction::Not);
+
+                Ok(())
+            }
+            Expression::Restriction(restriction) => {
+                // Restrictions distributed over a composite argument keep the span of the
+                // comparison holding it
+                let span = match subject {
+                    Some(_) => self.span,
+                    None => restriction.span,
+                };
+                let outer = std::mem::replace(&mut self.span, span);
+
+                match (&restriction.comparison, subject) {
+                    // Composite arguments only hold values the outer comparison applies to
+                    (Some(_), Some(_)) => {
+                        return Err(Error::NestedComparison {
+                            span: restriction.span,
+                        });
+                    }
+                    (Some((comparator, Arg::Composite(expression))), None) => {
+                        self.expression(expression, Some((&restriction.comparable, *comparator)))?;
+                    }
+                    (Some((comparator, arg)), None) => {
+                        self.operand(&restriction.comparable)?;
+                        self.arg(arg)?;
+                        self.emit(instruction(*comparator));
+                    }
+                    (None, Some((comparable, comparator))) => {
+                        self.operand(comparable)?;
+                        self.arg(&Arg::Comparable(restriction.comparable.clone()))?;
+                        self.emit(instruction(comparator));
+                    }
+                    (None, None) => {
+                        self.operand(&restriction.comparable)?;
+                        self.emit(Instruction::Test);
+                    }
+                }
+
+                self.span = outer;
+                Ok(())
+            }
+            Expression::Error(_) => Err(Error::Unparsed),
+        }
+    }
+
+    /// Compiles the operands of a short-circuiting operator, each one but the last being
+    /// followed by a jump to the end of the operator.
+    fn operands(
+        &mut self,
+        operands: &[Expression],
+        subject: Option<Subject>,
+        jump: fn(Address) -> Instruction,
+    ) -> Result<(), Error> {
+        let mut jumps = Vec::with_capacity(operands.len());
+
+        for (idx, operand) in operands.iter().enumerate() {
+            self.expression(operand, subject)?;
+
+            if idx + 1 < operands.len() {
+                jumps.push(self.filter.instructions.len());
+                self.emit(jump(0));
+            }
+        }
+
+        let end = self.address()?;
+        for idx in jumps {
+            self.filter.instructions[idx] = jump(end);
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the left-hand side of a comparison, or the argument of a function.
+    fn operand(&mut self, comparable: &Comparable) -> Result<(), Error> {
+        match comparable {
+            Comparable::Member(Member { value, fields })
+                if fields.is_empty() && !matches!(value, Value::Text(_)) =>
+            {
+                self.literal(value.clone())
+            }
+            Comparable::Member(member) => {
+                let path = path(member);
+                let index = intern(&mut self.filter.paths, path, |max| Error::TooManyPaths {
+                    max,
+                })?;
+                self.emit(Instruction::LoadPath(index));
+
+                Ok(())
+            }
+            Comparable::Function(function) => {
+                for arg in &function.args {
+                    match arg {
+                        Arg::Comparable(comparable) => self.operand(comparable)?,
+                        Arg::Composite(expression) => self.expression(expression, None)?,
+                        Arg::Error(_) => return Err(Error::Unparsed),
+                    }
+                }
+
+                let args = u8::try_from(function.args.len()).map_err(|_| {
+                    parser::Error::TooManyFunctionArgs {
+                        name: function.name.clone(),
+                        max: u8::MAX as usize,
+                    }
+                })?;
+                let function = intern(&mut self.filter.paths, function.name.clone(), |max| {
+                    Error::TooManyPaths { max }
+                })?;
+                self.emit(Instruction::Call { function, args });
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Compiles the right-hand side of a comparison.
+    fn arg(&mut self, arg: &Arg) -> Result<(), Error> {
+        match arg {
+            Arg::Comparable(Comparable::Member(member)) if member.fields.is_empty() => {
+                self.literal(member.value.clone())
+            }
+            Arg::Comparable(Comparable::Member(member)) => {
+                self.literal(Value::String(path(member)))
+            }
+            Arg::Comparable(comparable) => self.operand(comparable),
+            Arg::Composite(expression) => self.expression(expression, None),
+            Arg::Error(_) => Err(Error::Unparsed),
+        }
+    }
+
+    fn literal(&mut self, value: Value) -> Result<(), Error> {
+        let index = intern(&mut self.filter.literals, Literal::from(value), |max| {
+            Error::TooManyLiterals { max }
+        })?;
+        self.emit(Instruction::LoadLiteral(index));
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn emit(&mut self, instruction: Instruction) {
+        self.filter.instructions.push(instruction);
+        self.filter.spans.push(self.span);
+    }
+
+    /// Address of the next instruction.
+    #[inline]
+    fn address(&self) -> Result<Address, Error> {
+        Address::try_from(self.filter.instructions.len()).map_err(|_| Error::TooManyInstructions {
+            max: Address::MAX as usize,
+        })
+    }
+}
+
+/// Rejects the comparisons held by composite arguments, `nested` telling whether the
+/// expression is one, the composite arguments of functions being expressions of their own.
+fn reject_nested_comparisons(expression: &Expression, nested: bool) -> Result<(), Error> {
+    match expression {
+        Expression::And(operands) | Expression::Or(operands) | Expression::Sequence(operands) => {
+            operands
+                .iter()
+                .try_for_each(|operand| reject_nested_comparisons(operand, nested))
+        }
+        Expression::Not(operand) => reject_nested_comparisons(operand, nested),
+        Expression::Restriction(restriction) => {
+            if nested && restriction.comparison.is_some() {
+                return Err(Error::NestedComparison {
+                    span: restriction.span,
+                });
+            }
+
+            reject_nested_comparisons_in(&restriction.comparable)?;
+            match &restriction.comparison {
+                Some((_, Arg::Composite(expression))) => {
+                    reject_nested_comparisons(expression, true)
+                }
+                Some((_, Arg::Comparable(comparable))) => reject_nested_comparisons_in(comparable),
+                Some((_, Arg::Error(_))) | None => Ok(()),
+            }
+        }
+        Expression::Error(_) => Ok(()),
+    }
+}
+
+/// Rejects the comparisons nested in the composite arguments of a function's arguments.
+fn reject_nested_comparisons_in(comparable: &Comparable) -> Result<(), Error> {
+    let Comparable::Function(function) = comparable else {
+        return Ok(());
+    };
+
+    function.args.iter().try_for_each(|arg| match arg {
+        Arg::Comparable(comparable) => reject_nested_comparisons_in(comparable),
+        Arg::Composite(expression) => reject_nested_comparisons(expression, false),
+        Arg::Error(_) => Ok(()),
+    })
+}
+
+/// Index of a value in a register, the error of a full register being raised as the given
+/// one.
+#[inline]
+fn intern<T: Eq + Hash>(
+    register: &mut Register<T>,
+    value: T,
+    full: fn(usize) -> Error,
+) -> Result<Index, Error> {
+    register.intern(value).map_err(|error| match error {
+        RegisterError::Full { max } => full(max),
+        RegisterError::Duplicate { .. } => unreachable!("interning reuses duplicates"),
+    })
+}
+
+#[inline]
+fn path(member: &Member) -> String {
+    let root = match &member.value {
+        Value::Text(value) | Value::String(value) => value.clone(),
+        value => value.to_string(),
+    };
+
+    std::iter::once(root)
+        .chain(member.fields.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[inline]
+fn instruction(comparator: Comparator) -> Instruction {
+    match comparator {
+        Comparator::Equals => Instruction::Equals,
+        Comparator::NotEquals => Instruction::NotEquals,
+        Comparator::LesserThan => Instruction::LesserThan,
+        Comparator::LesserThanEquals => Instruction::LesserThanEquals,
+        Comparator::GreaterThan => Instruction::GreaterThan,
+        Comparator::GreaterThanEquals => Instruction::GreaterThanEquals,
+        Comparator::Has => Instruction::Has,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registers::IndexWidth;
+    use rapiere_parser::parse;
+    use rstest::rstest;
+    use Instruction::*;
+
+    #[rstest]
+    #[case::empty(b"", &[LoadLiteral(0)], &[Literal::Boolean(true)], &[])]
+    #[case::comparison(
+        b"a.b = 42",
+        &[LoadPath(0), LoadLiteral(0), Equals],
+        &[Literal::Integer(42)],
+        &["a.b"]
+    )]
+    #[case::enum_value(
+        b"state != ACTIVE",
+        &[LoadPath(0), LoadLiteral(0), NotEquals],
+        &[Literal::EnumValue("ACTIVE".to_owned())],
+        &["state"]
+    )]
+    #[case::timestamp(
+        b"create_time > \"2024-01-01T00:00:00Z\"",
+        &[LoadPath(0), LoadLiteral(0), GreaterThan],
+        &[Literal::Timestamp(chrono::DateTime::from_timestamp(1704067200, 0).unwrap().fixed_offset())],
+        &["create_time"]
+    )]
+    #[case::global_restriction(b"prod", &[LoadPath(0), Test], &[], &["prod"])]
+    #[case::global_literal(b"\"foo\"", &[LoadLiteral(0), Test], &[Literal::String("foo".to_owned())], &[])]
+    #[case::conjunction(
+        b"a > 1 b < 1 AND c:x",
+        &[
+            LoadPath(0), LoadLiteral(0), GreaterThan, JumpIfFalse(11),
+            LoadPath(1), LoadLiteral(0), LesserThan, JumpIfFalse(11),
+            LoadPath(2), LoadLiteral(1), Has,
+        ],
+        &[Literal::Integer(1), Literal::EnumValue("x".to_owned())],
+        &["a", "b", "c"]
+    )]
+    #[case::sorted_disjunction(
+        b"a OR NOT b",
+        &[LoadPath(0), Test, Not, JumpIfTrue(6), LoadPath(1), Test],
+        &[],
+        &["b", "a"]
+    )]
+    #[case::nested(
+        b"(a OR b) AND c",
+        &[LoadPath(0), Test, JumpIfTrue(5), LoadPath(1), Test, JumpIfFalse(8), LoadPath(2), Test],
+        &[],
+        &["a", "b", "c"]
+    )]
+    #[case::composite(
+        b"a = (1 OR 2)",
+        &[LoadPath(0), LoadLiteral(0), Equals, JumpIfTrue(7), LoadPath(0), LoadLiteral(1), Equals],
+        &[Literal::Integer(1), Literal::Integer(2)],
+        &["a"]
+    )]
+    #[case::function(
+        b"math.mem(a, 1) >= 2.5",
+        &[LoadPath(0), LoadLiteral(0), Call { function: 1, args: 2 }, LoadLiteral(1), GreaterThanEquals],
+        &[Literal::Integer(1), Literal::Float(2.5)],
+        &["a", "math.mem"]
+    )]
+    #[case::folded_constant(b"a AND NOT false", &[LoadPath(0), Test], &[], &["a"])]
+    fn it_compiles_a_filter(
+        #[case] input: &[u8],
+        #[case] expected_instructions: &[Instruction],
+        #[case] expected_literals: &[Literal],
+        #[case] expected_paths: &[&str],
+    ) {
+        let filter = compile(parse(input).unwrap());
+        assert!(filter.is_ok(), "{}", filter.unwrap_err());
+
+        let filter = filter.unwrap();
+        assert_eq!(filter.instructions(), expected_instructions);
+        assert_eq!(filter.literals(), expected_literals);
+        assert_eq!(filter.paths(), expected_paths);
+    }
+
+    #[rstest]
+    #[case::narrow(200, IndexWidth::U8)]
+    #[case::wide(300, IndexWidth::U16)]
+    fn it_compiles_filters_with_many_literals(#[case] len: usize, #[case] expected: IndexWidth) {
+        let input = (0..len)
+            .map(|value| format!("a = {value}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+
+        let filter = compile(parse(input.as_bytes()).unwrap());
+        assert!(filter.is_ok(), "{}", filter.unwrap_err());
+
+        let filter = filter.unwrap();
+        assert_eq!(filter.literals().len(), len);
+        assert_eq!(filter.index_width(), expected);
+        assert!(filter
+            .instructions()
+            .contains(&LoadLiteral(len as Index - 1)));
+    }
+
+    #[rstest]
+    #[case::comparison(b"a = (b > 1)", 5)]
+    #[case::composite(b"a = (b = (1 OR 2))", 5)]
+    #[case::operand(b"a:(1 OR NOT b:c)", 12)]
+    #[case::folded(b"true OR a = (b > 1)", 13)]
+    #[case::function_arg(b"f((a = (b > 1))) = 1", 8)]
+    fn it_rejects_a_nested_comparison(#[case] input: &[u8], #[case] offset: usize) {
+        let error = compile(parse(input).unwrap()).unwrap_err();
+
+        assert!(
+            matches!(error, Error::NestedComparison { span } if span.offset == offset),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn it_records_the_span_of_restrictions() {
+        let filter = compile(parse(b"a = (1 OR 2) AND b").unwrap()).unwrap();
+        let spans = (0..filter.instructions().len() as Address)
+            .map(|address| filter.span(address).map(|span| (span.offset, span.length)))
+            .collect::<Vec<_>>();
+
+        let mut expected = vec![Some((0, 12)); 7];
+        expected.extend([None, Some((17, 1)), Some((17, 1))]);
+        assert_eq!(spans, expected);
+    }
+}
@@ -0,0 +1,178 @@
+//! Human-readable listing of compiled filters, e.g.:
+//!
+//! ```text
+//! literals:
+//!   lit[0]   2024-01-01T00:00:00+00:00
+//! paths:
+//!   path[0]  create_time
+//! instructions:
+//!   0000 LOAD_PATH path[0]=create_time        ; 1:1 create_time > "2024-01-01T00:00:00Z"
+//!   0001 LOAD_LIT lit[0]=2024-01-01T00:00:00+00:00
+//!   0002 CMP_GT
+//! ```
+//!
+//! Each instruction compiled from another restriction than the previous one is annotated
+//! with the line and column of the restriction, as well as its text when the source of the
+//! filter is known.
+
+use crate::{filter::Filter, instruction::Instruction};
+use std::fmt::{self, Write};
+
+/// Width of the instruction column, before annotations.
+const WIDTH: usize = 44;
+
+/// Disassembly of a filter, displayed as its register tables and annotated program.
+#[derive(Clone, Copy, Debug)]
+pub struct Disassembly<'f> {
+    filter: &'f Filter,
+    source: Option<&'f [u8]>,
+}
+
+impl Filter {
+    /// Disassembles the filter, annotating its instructions with the text of the
+    /// restrictions they were compiled from.
+    #[inline(always)]
+    pub fn disassemble<'f>(&'f self, source: &'f [u8]) -> Disassembly<'f> {
+        Disassembly {
+            filter: self,
+            source: Some(source),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    /// Disassembles the filter, its source being unknown.
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Disassembly {
+            filter: self,
+            source: None,
+        }
+        .fmt(f)
+    }
+}
+
+impl Disassembly<'_> {
+    /// Instruction with the values of the registers it refers to.
+    fn instruction(&self, instruction: &Instruction) -> String {
+        let path = |index| match self.filter.paths().get(index as usize) {
+            Some(path) => format!("path[{index}]={path}"),
+            None => format!("path[{index}]=?"),
+        };
+
+        match *instruction {
+            Instruction::LoadPath(index) => format!("{} {}", instruction.mnemonic(), path(index)),
+            Instruction::LoadLiteral(index) => match self.filter.literals().get(index as usize) {
+                Some(literal) => format!("{} lit[{index}]={literal}", instruction.mnemonic()),
+                None => format!("{} lit[{index}]=?", instruction.mnemonic()),
+            },
+            Instruction::Call { function, args } => {
+                format!("{} {} args={args}", instruction.mnemonic(), path(function))
+            }
+            instruction => instruction.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "literals:")?;
+        for (index, literal) in self.filter.literals().iter().enumerate() {
+            writeln!(f, "  {:<8} {literal}", format!("lit[{index}]"))?;
+        }
+
+        writeln!(f, "paths:")?;
+        for (index, path) in self.filter.paths().iter().enumerate() {
+            writeln!(f, "  {:<8} {path}", format!("path[{index}]"))?;
+        }
+
+        writeln!(f, "instructions:")?;
+        let mut previous = None;
+        for (address, instruction) in (0..).zip(self.filter.instructions()) {
+            let mut line = format!("  {address:04} {}", self.instruction(instruction));
+
+            let span = self.filter.span(address);
+            if let Some(span) = span.filter(|_| span != previous) {
+                write!(
+                    line,
+                    "{:width$}; {}:{}",
+                    "",
+                    span.line,
+                    span.column,
+                    width = WIDTH.saturating_sub(line.len()).max(1)
+                )?;
+
+                let text = self
+                    .source
+                    .and_then(|source| source.get(span.offset..span.end()));
+                if let Some(text) = text {
+                    write!(line, " {}", String::from_utf8_lossy(text))?;
+                }
+            }
+            previous = span;
+
+            writeln!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile::compile;
+    use rapiere_parser::parse;
+
+    #[test]
+    fn it_disassembles_a_filter() {
+        let input = b"create_time > \"2024-01-01T00:00:00Z\" AND\n  labels:(env OR team)";
+        let filter = compile(parse(input).unwrap()).unwrap();
+
+        let expected = "\
+literals:
+  lit[0]   2024-01-01T00:00:00+00:00
+  lit[1]   env
+  lit[2]   team
+paths:
+  path[0]  create_time
+  path[1]  labels
+instructions:
+  0000 LOAD_PATH path[0]=create_time        ; 1:1 create_time > \"2024-01-01T00:00:00Z\"
+  0001 LOAD_LIT lit[0]=2024-01-01T00:00:00+00:00
+  0002 CMP_GT
+  0003 JMP_FALSE 0011
+  0004 LOAD_PATH path[1]=labels             ; 2:3 labels:(env OR team)
+  0005 LOAD_LIT lit[1]=env
+  0006 HAS
+  0007 JMP_TRUE 0011
+  0008 LOAD_PATH path[1]=labels
+  0009 LOAD_LIT lit[2]=team
+  0010 HAS
+";
+        assert_eq!(filter.disassemble(input).to_string(), expected);
+    }
+
+    #[test]
+    fn it_displays_a_filter_without_its_source() {
+        let filter = compile(parse(b"f(a, 1) OR NOT b").unwrap()).unwrap();
+
+        let expected = "\
+literals:
+  lit[0]   1
+paths:
+  path[0]  b
+  path[1]  a
+  path[2]  f
+instructions:
+  0000 LOAD_PATH path[0]=b                  ; 1:16
+  0001 TEST
+  0002 NOT
+  0003 JMP_TRUE 0008
+  0004 LOAD_PATH path[1]=a                  ; 1:1
+  0005 LOAD_LIT lit[0]=1
+  0006 CALL path[2]=f args=2
+  0007 TEST
+";
+        assert_eq!(filter.to_string(), expected);
+    }
+}
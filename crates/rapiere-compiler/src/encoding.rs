@@ -0,0 +1,733 @@
+//! Versioned binary encoding of compiled filters, e.g. for shipping them to other processes
+//! or caching them.
+//!
+//! An encoded filter starts with the [`Filter::MAGIC`] bytes, then holds:
+//! - the [`Filter::FORMAT_VERSION`] byte;
+//! - the width of its register indices, in bytes;
+//! - its literal register, as a count followed by the literals, each one being a tag byte
+//!   followed by its value;
+//! - its path register, as a count followed by the paths;
+//! - its program, as a count followed by the instructions, each one being an opcode byte
+//!   followed by its operands.
+//!
+//! Counts, lengths and addresses are LEB128 variable-length integers, indices are written
+//! with the filter's index width, and numbers are little-endian.
+//!
+//! Encoded filters coming from an untrusted source should be decoded with [`DecodeLimits`],
+//! counts above them being rejected before any value is decoded.
+
+use crate::{
+    error::DecodeError,
+    filter::Filter,
+    instruction::{Address, Instruction},
+    registers::{Index, IndexWidth, Literal, Register},
+};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+
+mod tag {
+    pub const BOOLEAN: u8 = 0;
+    pub const BYTES: u8 = 1;
+    pub const DURATION: u8 = 2;
+    pub const ENUM_VALUE: u8 = 3;
+    pub const FLOAT: u8 = 4;
+    pub const INTEGER: u8 = 5;
+    pub const STRING: u8 = 6;
+    pub const TIMESTAMP: u8 = 7;
+    pub const NULL: u8 = 8;
+}
+
+mod opcode {
+    pub const LOAD_PATH: u8 = 0;
+    pub const LOAD_LITERAL: u8 = 1;
+    pub const EQUALS: u8 = 2;
+    pub const NOT_EQUALS: u8 = 3;
+    pub const LESSER_THAN: u8 = 4;
+    pub const LESSER_THAN_EQUALS: u8 = 5;
+    pub const GREATER_THAN: u8 = 6;
+    pub const GREATER_THAN_EQUALS: u8 = 7;
+    pub const HAS: u8 = 8;
+    pub const CALL: u8 = 9;
+    pub const TEST: u8 = 10;
+    pub const NOT: u8 = 11;
+    pub const JUMP_IF_FALSE: u8 = 12;
+    pub const JUMP_IF_TRUE: u8 = 13;
+}
+
+/// Caps on the size of the filters decoded, each one defaulting to the largest filter a
+/// compiler can produce.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DecodeLimits {
+    max_literals: usize,
+    max_paths: usize,
+    max_instructions: usize,
+}
+
+impl DecodeLimits {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn with_max_literals(mut self, max_literals: usize) -> Self {
+        self.max_literals = max_literals;
+        self
+    }
+
+    /// Caps the number of paths, the names of functions included.
+    #[inline]
+    pub fn with_max_paths(mut self, max_paths: usize) -> Self {
+        self.max_paths = max_paths;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_instructions(mut self, max_instructions: usize) -> Self {
+        self.max_instructions = max_instructions;
+        self
+    }
+
+    #[inline(always)]
+    pub fn max_literals(&self) -> usize {
+        self.max_literals
+    }
+
+    #[inline(always)]
+    pub fn max_paths(&self) -> usize {
+        self.max_paths
+    }
+
+    #[inline(always)]
+    pub fn max_instructions(&self) -> usize {
+        self.max_instructions
+    }
+}
+
+impl Default for DecodeLimits {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_literals: Register::<Literal>::MAX_SIZE,
+            max_paths: Register::<String>::MAX_SIZE,
+            max_instructions: Address::MAX as usize,
+        }
+    }
+}
+
+impl Filter {
+    pub const MAGIC: [u8; 4] = *b"RPRF";
+    pub const FORMAT_VERSION: u8 = 1;
+
+    /// Encodes the filter, its indices having the narrowest width fitting its registers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let width = self.index_width();
+        let mut encoder = Encoder {
+            bytes: Vec::from(Self::MAGIC),
+            width,
+        };
+        encoder.bytes.push(Self::FORMAT_VERSION);
+        encoder.bytes.push(width.bytes() as u8);
+
+        encoder.len(self.literals.len());
+        for literal in self.literals() {
+            encoder.literal(literal);
+        }
+
+        encoder.len(self.paths.len());
+        for path in self.paths() {
+            encoder.str(path);
+        }
+
+        encoder.len(self.instructions.len());
+        for instruction in self.instructions() {
+            encoder.instruction(instruction);
+        }
+
+        encoder.bytes
+    }
+
+    /// Decodes a filter, validating that its instructions refer to the indices its
+    /// registers hold and jump forward within its program.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Filter, DecodeError> {
+        Self::from_bytes_with_limits(bytes, DecodeLimits::default())
+    }
+
+    /// Decodes a filter as [`Filter::from_bytes`] does, rejecting it as soon as one of its
+    /// counts exceeds the limits.
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: DecodeLimits,
+    ) -> Result<Filter, DecodeError> {
+        let mut decoder = Decoder {
+            bytes,
+            offset: 0,
+            width: IndexWidth::U8,
+        };
+
+        if decoder.take(Self::MAGIC.len())? != Self::MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+        match decoder.u8()? {
+            Self::FORMAT_VERSION => {}
+            version => return Err(DecodeError::UnsupportedVersion(version)),
+        }
+        decoder.width = match decoder.u8()? {
+            1 => IndexWidth::U8,
+            2 => IndexWidth::U16,
+            4 => IndexWidth::U32,
+            byte => return Err(decoder.invalid_byte(byte)),
+        };
+
+        let mut filter = Filter::new();
+        let len = decoder.len()?;
+        if len > limits.max_literals {
+            return Err(DecodeError::TooManyLiterals {
+                len,
+                max: limits.max_literals,
+            });
+        }
+        for _ in 0..len {
+            filter.literals.push(decoder.literal()?)?;
+        }
+
+        let len = decoder.len()?;
+        if len > limits.max_paths {
+            return Err(DecodeError::TooManyPaths {
+                len,
+                max: limits.max_paths,
+            });
+        }
+        for _ in 0..len {
+            let path = decoder.string()?;
+            filter.paths.push(path)?;
+        }
+
+        let len = decoder.len()?;
+        if len > limits.max_instructions {
+            return Err(DecodeError::TooManyInstructions {
+                len,
+                max: limits.max_instructions,
+            });
+        }
+        // Every instruction is at least one byte long
+        filter.instructions = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            filter.instructions.push(decoder.instruction()?);
+        }
+
+        if decoder.offset < bytes.len() {
+            return Err(DecodeError::TrailingBytes {
+                offset: decoder.offset,
+            });
+        }
+        validate(&filter)?;
+
+        Ok(filter)
+    }
+}
+
+/// Checks the indices and jump targets of the filter's instructions.
+fn validate(filter: &Filter) -> Result<(), DecodeError> {
+    let len = filter.instructions.len();
+    let address = Address::try_from(len).map_err(|_| DecodeError::InvalidJump {
+        address: Address::MAX,
+    })?;
+
+    for (at, instruction) in (0..address).zip(filter.instructions()) {
+        let in_bounds = |index: Index, register_len: usize| (index as usize) < register_len;
+
+        match *instruction {
+            Instruction::LoadPath(index)
+            | Instruction::Call {
+                function: index, ..
+            } if !in_bounds(index, filter.paths.len()) => {
+                return Err(DecodeError::IndexOutOfBounds { address: at });
+            }
+            Instruction::LoadLiteral(index) if !in_bounds(index, filter.literals.len()) => {
+                return Err(DecodeError::IndexOutOfBounds { address: at });
+            }
+            Instruction::JumpIfFalse(target) | Instruction::JumpIfTrue(target)
+                if target <= at || target > address =>
+            {
+                return Err(DecodeError::InvalidJump { address: at });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+    width: IndexWidth,
+}
+
+impl Encoder {
+    /// Writes an unsigned LEB128 integer.
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    #[inline(always)]
+    fn len(&mut self, len: usize) {
+        self.varint(len as u64);
+    }
+
+    #[inline]
+    fn index(&mut self, index: Index) {
+        let bytes = index.to_le_bytes();
+        self.bytes.extend_from_slice(&bytes[..self.width.bytes()]);
+    }
+
+    #[inline]
+    fn slice(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    #[inline(always)]
+    fn str(&mut self, value: &str) {
+        self.slice(value.as_bytes());
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Boolean(value) => self.bytes.extend([tag::BOOLEAN, *value as u8]),
+            Literal::Bytes(value) => {
+                self.bytes.push(tag::BYTES);
+                self.slice(value);
+            }
+            Literal::Duration(value) => {
+                self.bytes.push(tag::DURATION);
+                self.bytes.extend(value.num_seconds().to_le_bytes());
+                self.bytes.extend(value.subsec_nanos().to_le_bytes());
+            }
+            Literal::EnumValue(value) => {
+                self.bytes.push(tag::ENUM_VALUE);
+                self.str(value);
+            }
+            Literal::Float(value) => {
+                self.bytes.push(tag::FLOAT);
+                self.bytes.extend(value.to_le_bytes());
+            }
+            Literal::Integer(value) => {
+                self.bytes.push(tag::INTEGER);
+                self.bytes.extend(value.to_le_bytes());
+            }
+            Literal::String(value) => {
+                self.bytes.push(tag::STRING);
+                self.str(value);
+            }
+            Literal::Timestamp(value) => {
+                self.bytes.push(tag::TIMESTAMP);
+                self.bytes.extend(value.timestamp().to_le_bytes());
+                self.bytes
+                    .extend(value.timestamp_subsec_nanos().to_le_bytes());
+                self.bytes
+                    .extend(value.offset().local_minus_utc().to_le_bytes());
+            }
+            Literal::Null => self.bytes.push(tag::NULL),
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match *instruction {
+            Instruction::LoadPath(index) => {
+                self.bytes.push(opcode::LOAD_PATH);
+                self.index(index);
+            }
+            Instruction::LoadLiteral(index) => {
+                self.bytes.push(opcode::LOAD_LITERAL);
+                self.index(index);
+            }
+            Instruction::Equals => self.bytes.push(opcode::EQUALS),
+            Instruction::NotEquals => self.bytes.push(opcode::NOT_EQUALS),
+            Instruction::LesserThan => self.bytes.push(opcode::LESSER_THAN),
+            Instruction::LesserThanEquals => self.bytes.push(opcode::LESSER_THAN_EQUALS),
+            Instruction::GreaterThan => self.bytes.push(opcode::GREATER_THAN),
+            Instruction::GreaterThanEquals => self.bytes.push(opcode::GREATER_THAN_EQUALS),
+            Instruction::Has => self.bytes.push(opcode::HAS),
+            Instruction::Call { function, args } => {
+                self.bytes.push(opcode::CALL);
+                self.index(function);
+                self.bytes.push(args);
+            }
+            Instruction::Test => self.bytes.push(opcode::TEST),
+            Instruction::Not => self.bytes.push(opcode::NOT),
+            Instruction::JumpIfFalse(target) => {
+                self.bytes.push(opcode::JUMP_IF_FALSE);
+                self.varint(target as u64);
+            }
+            Instruction::JumpIfTrue(target) => {
+                self.bytes.push(opcode::JUMP_IF_TRUE);
+                self.varint(target as u64);
+            }
+        }
+    }
+}
+
+struct Decoder<'b> {
+    bytes: &'b [u8],
+    offset: usize,
+    width: IndexWidth,
+}
+
+impl<'b> Decoder<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DecodeError> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.offset..end))
+            .ok_or(DecodeError::UnexpectedEnd {
+                offset: self.bytes.len(),
+            })?;
+        self.offset += len;
+
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+
+        Ok(array)
+    }
+
+    #[inline(always)]
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Error of the byte which was just read.
+    #[inline(always)]
+    fn invalid_byte(&self, byte: u8) -> DecodeError {
+        DecodeError::InvalidByte {
+            offset: self.offset - 1,
+            byte,
+        }
+    }
+
+    /// Reads an unsigned LEB128 integer.
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let start = self.offset;
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(DecodeError::InvalidValue { offset: start })
+    }
+
+    #[inline]
+    fn len(&mut self) -> Result<usize, DecodeError> {
+        let start = self.offset;
+
+        usize::try_from(self.varint()?).map_err(|_| DecodeError::InvalidValue { offset: start })
+    }
+
+    #[inline]
+    fn index(&mut self) -> Result<Index, DecodeError> {
+        let mut bytes = [0; size_of::<Index>()];
+        bytes[..self.width.bytes()].copy_from_slice(self.take(self.width.bytes())?);
+
+        Ok(Index::from_le_bytes(bytes))
+    }
+
+    #[inline]
+    fn address(&mut self) -> Result<Address, DecodeError> {
+        let start = self.offset;
+
+        Address::try_from(self.varint()?).map_err(|_| DecodeError::InvalidValue { offset: start })
+    }
+
+    #[inline]
+    fn slice(&mut self) -> Result<&'b [u8], DecodeError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let start = self.offset;
+
+        std::str::from_utf8(self.slice()?)
+            .map(str::to_owned)
+            .map_err(|_| DecodeError::InvalidValue { offset: start })
+    }
+
+    fn literal(&mut self) -> Result<Literal, DecodeError> {
+        let literal = match self.u8()? {
+            tag::BOOLEAN => match self.u8()? {
+                0 => Literal::Boolean(false),
+                1 => Literal::Boolean(true),
+                byte => return Err(self.invalid_byte(byte)),
+            },
+            tag::BYTES => Literal::Bytes(self.slice()?.to_vec()),
+            tag::DURATION => {
+                let start = self.offset;
+                let seconds = i64::from_le_bytes(self.array()?);
+                let nanos = i32::from_le_bytes(self.array()?);
+
+                let duration = TimeDelta::try_seconds(seconds)
+                    .and_then(|duration| {
+                        duration.checked_add(&TimeDelta::nanoseconds(nanos as i64))
+                    })
+                    .filter(|_| nanos.unsigned_abs() < 1_000_000_000)
+                    .ok_or(DecodeError::InvalidValue { offset: start })?;
+                Literal::Duration(duration)
+            }
+            tag::ENUM_VALUE => Literal::EnumValue(self.string()?),
+            tag::FLOAT => Literal::Float(f32::from_le_bytes(self.array()?)),
+            tag::INTEGER => Literal::Integer(i64::from_le_bytes(self.array()?)),
+            tag::STRING => Literal::String(self.string()?),
+            tag::TIMESTAMP => {
+                let start = self.offset;
+                let seconds = i64::from_le_bytes(self.array()?);
+                let nanos = u32::from_le_bytes(self.array()?);
+                let offset = i32::from_le_bytes(self.array()?);
+
+                let timestamp = DateTime::from_timestamp(seconds, nanos)
+                    .zip(FixedOffset::east_opt(offset))
+                    .map(|(timestamp, offset)| timestamp.with_timezone(&offset))
+                    .ok_or(DecodeError::InvalidValue { offset: start })?;
+                Literal::Timestamp(timestamp)
+            }
+            tag::NULL => Literal::Null,
+            byte => return Err(self.invalid_byte(byte)),
+        };
+
+        Ok(literal)
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, DecodeError> {
+        let instruction = match self.u8()? {
+            opcode::LOAD_PATH => Instruction::LoadPath(self.index()?),
+            opcode::LOAD_LITERAL => Instruction::LoadLiteral(self.index()?),
+            opcode::EQUALS => Instruction::Equals,
+            opcode::NOT_EQUALS => Instruction::NotEquals,
+            opcode::LESSER_THAN => Instruction::LesserThan,
+            opcode::LESSER_THAN_EQUALS => Instruction::LesserThanEquals,
+            opcode::GREATER_THAN => Instruction::GreaterThan,
+            opcode::GREATER_THAN_EQUALS => Instruction::GreaterThanEquals,
+            opcode::HAS => Instruction::Has,
+            opcode::CALL => Instruction::Call {
+                function: self.index()?,
+                args: self.u8()?,
+            },
+            opcode::TEST => Instruction::Test,
+            opcode::NOT => Instruction::Not,
+            opcode::JUMP_IF_FALSE => Instruction::JumpIfFalse(self.address()?),
+            opcode::JUMP_IF_TRUE => Instruction::JumpIfTrue(self.address()?),
+            byte => return Err(self.invalid_byte(byte)),
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile::compile, error::RegisterError};
+    use rapiere_parser::parse;
+    use rstest::rstest;
+
+    fn assert_same(decoded: &Filter, filter: &Filter) {
+        assert_eq!(decoded.instructions(), filter.instructions());
+        assert_eq!(decoded.literals(), filter.literals());
+        assert_eq!(decoded.paths(), filter.paths());
+    }
+
+    #[rstest]
+    #[case::empty(b"")]
+    #[case::comparison(b"a.b = 42 AND c != ACTIVE")]
+    #[case::nested(b"(a OR NOT b) AND c:\"x*\" AND d <= -1.5")]
+    #[case::function(b"math.mem(a, 1) >= 2.5 OR f() = null")]
+    #[case::time(b"create_time > \"2024-01-01T00:00:00+02:00\" AND ttl < \"-3.25s\"")]
+    fn it_encodes_a_filter(#[case] input: &[u8]) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+        let bytes = filter.to_bytes();
+
+        assert_eq!(bytes[..4], Filter::MAGIC);
+        assert_eq!(bytes[4], Filter::FORMAT_VERSION);
+
+        let decoded = Filter::from_bytes(&bytes);
+        assert!(decoded.is_ok(), "{}", decoded.unwrap_err());
+        assert_same(&decoded.unwrap(), &filter);
+    }
+
+    #[test]
+    fn it_encodes_every_literal() {
+        let mut filter = Filter::new();
+        let literals = [
+            Literal::Boolean(true),
+            Literal::Bytes(vec![0, 0xff]),
+            Literal::Duration(TimeDelta::milliseconds(-1500)),
+            Literal::EnumValue("ACTIVE".to_owned()),
+            Literal::Float(f32::NAN),
+            Literal::Integer(i64::MIN),
+            Literal::String("é".to_owned()),
+            Literal::Timestamp(
+                DateTime::parse_from_rfc3339("2024-01-01T00:00:00.5-05:30").unwrap(),
+            ),
+            Literal::Null,
+        ];
+        for (index, literal) in literals.into_iter().enumerate() {
+            filter.literals.push(literal).unwrap();
+            filter
+                .instructions
+                .push(Instruction::LoadLiteral(index as Index));
+        }
+
+        let decoded = Filter::from_bytes(&filter.to_bytes()).unwrap();
+        assert_same(&decoded, &filter);
+        let Literal::Timestamp(timestamp) = &decoded.literals()[7] else {
+            unreachable!()
+        };
+        assert_eq!(timestamp.offset().local_minus_utc(), -19800);
+    }
+
+    #[test]
+    fn it_encodes_wide_indices() {
+        let input = (0..300)
+            .map(|value| format!("a = {value}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let filter = compile(parse(input.as_bytes()).unwrap()).unwrap();
+        let bytes = filter.to_bytes();
+
+        assert_eq!(bytes[5], 2);
+        assert_same(&Filter::from_bytes(&bytes).unwrap(), &filter);
+    }
+
+    /// Encodes a filter loading each of the given number of paths.
+    fn encode_paths(len: usize) -> Vec<u8> {
+        let mut filter = Filter::new();
+        for index in 0..len {
+            filter.paths.push(format!("p{index}")).unwrap();
+            filter
+                .instructions
+                .push(Instruction::LoadPath(index as Index));
+        }
+
+        filter.to_bytes()
+    }
+
+    #[test]
+    fn it_decodes_many_paths() {
+        let bytes = encode_paths(100_000);
+        let decoded = Filter::from_bytes(&bytes).unwrap();
+
+        // Decoded paths are interned as they were encoded, each one at its own index
+        assert_eq!(decoded.paths().len(), 100_000);
+        assert_eq!(decoded.instructions().len(), 100_000);
+        for index in [0, 1, 99_999] {
+            let path = format!("p{index}");
+            assert_eq!(decoded.paths.position(&path), Some(index as Index));
+            assert_eq!(
+                decoded.instructions()[index],
+                Instruction::LoadPath(index as Index)
+            );
+        }
+        assert_eq!(decoded.to_bytes(), bytes);
+    }
+
+    #[rstest]
+    #[case::literals(
+        DecodeLimits::new().with_max_literals(1),
+        encode(&[], &[Literal::Null, Literal::Boolean(true)]),
+        DecodeError::TooManyLiterals { len: 2, max: 1 }
+    )]
+    #[case::paths(
+        DecodeLimits::new().with_max_paths(99),
+        encode_paths(100),
+        DecodeError::TooManyPaths { len: 100, max: 99 }
+    )]
+    #[case::instructions(
+        DecodeLimits::new().with_max_instructions(1),
+        encode(&[Instruction::LoadPath(0), Instruction::Test], &[]),
+        DecodeError::TooManyInstructions { len: 2, max: 1 }
+    )]
+    // Counts are checked before the values they count are decoded
+    #[case::truncated(
+        DecodeLimits::new().with_max_paths(1),
+        b"RPRF\x01\x01\x00\xff\xff\x03".to_vec(),
+        DecodeError::TooManyPaths { len: 65535, max: 1 }
+    )]
+    fn it_enforces_limits(
+        #[case] limits: DecodeLimits,
+        #[case] bytes: Vec<u8>,
+        #[case] expected: DecodeError,
+    ) {
+        assert_eq!(
+            Filter::from_bytes_with_limits(&bytes, limits).unwrap_err(),
+            expected
+        );
+    }
+
+    fn encode(instructions: &[Instruction], literals: &[Literal]) -> Vec<u8> {
+        let mut encoder = Encoder {
+            bytes: Vec::from(Filter::MAGIC),
+            width: IndexWidth::U8,
+        };
+        encoder.bytes.extend([Filter::FORMAT_VERSION, 1]);
+
+        encoder.len(literals.len());
+        for literal in literals {
+            encoder.literal(literal);
+        }
+        encoder.len(1);
+        encoder.str("a");
+        encoder.len(instructions.len());
+        for instruction in instructions {
+            encoder.instruction(instruction);
+        }
+
+        encoder.bytes
+    }
+
+    #[rstest]
+    #[case::magic(b"RPRG\x01\x01\x00\x00\x00".to_vec(), DecodeError::InvalidMagic)]
+    #[case::version(b"RPRF\x02\x01\x00\x00\x00".to_vec(), DecodeError::UnsupportedVersion(2))]
+    #[case::width(b"RPRF\x01\x03\x00\x00\x00".to_vec(), DecodeError::InvalidByte { offset: 5, byte: 3 })]
+    #[case::truncated(b"RPRF\x01\x01\x01\x05\x2a".to_vec(), DecodeError::UnexpectedEnd { offset: 9 })]
+    #[case::trailing(b"RPRF\x01\x01\x00\x00\x00\x00".to_vec(), DecodeError::TrailingBytes { offset: 9 })]
+    #[case::tag(b"RPRF\x01\x01\x01\x2a".to_vec(), DecodeError::InvalidByte { offset: 7, byte: 0x2a })]
+    #[case::utf8(b"RPRF\x01\x01\x01\x06\x01\xff".to_vec(), DecodeError::InvalidValue { offset: 8 })]
+    #[case::opcode(b"RPRF\x01\x01\x00\x00\x01\xff".to_vec(), DecodeError::InvalidByte { offset: 9, byte: 0xff })]
+    #[case::duplicate(
+        encode(&[], &[Literal::Null, Literal::Null]),
+        DecodeError::Register(RegisterError::Duplicate { index: 0 })
+    )]
+    #[case::literal_index(
+        encode(&[Instruction::LoadLiteral(1)], &[Literal::Null]),
+        DecodeError::IndexOutOfBounds { address: 0 }
+    )]
+    #[case::path_index(
+        encode(&[Instruction::Call { function: 1, args: 0 }], &[]),
+        DecodeError::IndexOutOfBounds { address: 0 }
+    )]
+    #[case::backward_jump(
+        encode(&[Instruction::LoadPath(0), Instruction::JumpIfTrue(1)], &[]),
+        DecodeError::InvalidJump { address: 1 }
+    )]
+    #[case::jump_past_end(
+        encode(&[Instruction::LoadPath(0), Instruction::JumpIfTrue(3)], &[]),
+        DecodeError::InvalidJump { address: 1 }
+    )]
+    fn it_rejects_an_invalid_encoding(#[case] bytes: Vec<u8>, #[case] expected: DecodeError) {
+        assert_eq!(Filter::from_bytes(&bytes).unwrap_err(), expected);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{instruction::Address, registers::Index};
+use rapiere_lexer::Span;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("normal form would hold {clauses} clauses, more than the maximum of {max}")]
+    NormalFormTooLarge { clauses: usize, max: usize },
+
+    #[error(transparent)]
+    Parse(#[from] rapiere_parser::Error),
+
+    #[error("filter holds expressions which could not be parsed")]
+    Unparsed,
+
+    /// A comparison within the composite argument of another one, e.g. `a = (b > 1)`
+    #[error(
+        "comparison nested in a composite argument (line: {}, column: {})",
+        span.line,
+        span.column
+    )]
+    NestedComparison { span: Span },
+
+    #[error("filter exceeds the maximum of {max} literals")]
+    TooManyLiterals { max: usize },
+
+    #[error("filter exceeds the maximum of {max} paths and functions")]
+    TooManyPaths { max: usize },
+
+    #[error("filter exceeds the maximum of {max} instructions")]
+    TooManyInstructions { max: usize },
+}
+
+/// An error raised while pushing a value into a register.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum RegisterError {
+    #[error("register is full, holding the maximum of {max} values")]
+    Full { max: usize },
+
+    /// The register already holds the value, at the given index
+    #[error("register already holds the value at index {index}")]
+    Duplicate { index: Index },
+}
+
+/// An error raised while decoding a compiled filter from its binary encoding.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("input is not an encoded filter")]
+    InvalidMagic,
+
+    #[error("unsupported format version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("unexpected end of input at offset {offset}")]
+    UnexpectedEnd { offset: usize },
+
+    #[error("unexpected bytes after the filter at offset {offset}")]
+    TrailingBytes { offset: usize },
+
+    #[error("encoded filter holds {len} literals, more than the maximum of {max}")]
+    TooManyLiterals { len: usize, max: usize },
+
+    #[error("encoded filter holds {len} paths and functions, more than the maximum of {max}")]
+    TooManyPaths { len: usize, max: usize },
+
+    #[error("encoded filter holds {len} instructions, more than the maximum of {max}")]
+    TooManyInstructions { len: usize, max: usize },
+
+    /// An unknown index width, literal tag or opcode
+    #[error("invalid byte {byte:#04x} at offset {offset}")]
+    InvalidByte { offset: usize, byte: u8 },
+
+    /// A string which is not UTF-8, or a timestamp or a duration out of range
+    #[error("invalid value at offset {offset}")]
+    InvalidValue { offset: usize },
+
+    #[error(transparent)]
+    Register(#[from] RegisterError),
+
+    #[error("instruction at address {address} refers to an index out of its register")]
+    IndexOutOfBounds { address: Address },
+
+    #[error("instruction at address {address} jumps to an invalid address")]
+    InvalidJump { address: Address },
+}
+
+/// An error raised while evaluating a compiled filter against a record.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum EvalError {
+    /// The program does not leave a single condition on the stack, or refers to registers
+    /// or addresses it does not hold
+    #[error("malformed program at address {address}")]
+    Malformed { address: Address },
+
+    /// A list or a map is compared with another operator than `:`
+    #[error("operands of the instruction at address {address} are not comparable")]
+    NotComparable { address: Address },
+
+    /// The interpreted filter holds expressions which could not be parsed
+    #[error("filter holds expressions which could not be parsed")]
+    Unparsed,
+
+    /// The interpreted filter holds a comparison within the composite argument of another
+    /// one, e.g. `a = (b > 1)`
+    #[error(
+        "comparison nested in a composite argument (line: {}, column: {})",
+        span.line,
+        span.column
+    )]
+    NestedComparison { span: Span },
+
+    #[error("unknown function `{0}`")]
+    UnknownFunction(String),
+
+    #[error("function `{name}` failed: {message}")]
+    Function { name: String, message: String },
+}
@@ -1,9 +1,19 @@
-use crate::registers::{LiteralRegister, PathRegister};
+use crate::{
+    instruction::{Address, Instruction},
+    registers::{IndexWidth, Literal, LiteralRegister, PathRegister},
+};
+use rapiere_lexer::Span;
 
+/// A compiled filter, the program evaluating it along with the registers its instructions
+/// refer to.
 #[derive(Clone, Debug, Default)]
 pub struct Filter {
-    literals: LiteralRegister,
-    paths: PathRegister,
+    pub(crate) instructions: Vec<Instruction>,
+    pub(crate) literals: LiteralRegister,
+    pub(crate) paths: PathRegister,
+
+    /// Span of the restriction each instruction was compiled from, empty when unknown
+    pub(crate) spans: Vec<Span>,
 }
 
 impl Filter {
@@ -11,4 +21,36 @@ impl Filter {
     pub fn new() -> Self {
         Default::default()
     }
+
+    #[inline(always)]
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    #[inline(always)]
+    pub fn literals(&self) -> &[Literal] {
+        self.literals.as_slice()
+    }
+
+    /// Paths of the record, as well as the names of the functions called.
+    #[inline(always)]
+    pub fn paths(&self) -> &[String] {
+        self.paths.as_slice()
+    }
+
+    /// Narrowest width fitting the indices of both registers.
+    #[inline]
+    pub fn index_width(&self) -> IndexWidth {
+        IndexWidth::of(self.literals.len().max(self.paths.len()))
+    }
+
+    /// Span of the restriction the instruction at the address was compiled from, unknown
+    /// for the instructions of operators and for decoded filters.
+    #[inline]
+    pub fn span(&self, address: Address) -> Option<Span> {
+        self.spans
+            .get(address as usize)
+            .copied()
+            .filter(|span| span.length > 0)
+    }
 }
@@ -0,0 +1,97 @@
+use crate::registers::Index;
+use std::fmt;
+
+/// Position of an instruction in a filter's program.
+pub type Address = u32;
+
+/// An instruction of the stack machine evaluating compiled filters.
+///
+/// Instructions pop their operands from the stack, the right-hand one being on top, then
+/// push their result.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Instruction {
+    /// Pushes the value of the record at the path of the path register
+    LoadPath(Index),
+
+    /// Pushes the literal of the literal register
+    LoadLiteral(Index),
+
+    /// =
+    Equals,
+
+    /// !=
+    NotEquals,
+
+    /// <
+    LesserThan,
+
+    /// <=
+    LesserThanEquals,
+
+    /// >
+    GreaterThan,
+
+    /// >=
+    GreaterThanEquals,
+
+    /// :
+    Has,
+
+    /// Pops the arguments of the function named in the path register, then pushes the
+    /// result of the call
+    Call {
+        function: Index,
+        args: u8,
+    },
+
+    /// Pops a value, then pushes whether it is truthy, for global restrictions
+    Test,
+
+    Not,
+
+    /// Jumps if the condition on top of the stack is false, keeping it as the result of a
+    /// short-circuited `AND`, or pops it otherwise
+    JumpIfFalse(Address),
+
+    /// Jumps if the condition on top of the stack is true, keeping it as the result of a
+    /// short-circuited `OR`, or pops it otherwise
+    JumpIfTrue(Address),
+}
+
+impl Instruction {
+    /// Name of the instruction in disassemblies.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::LoadPath(_) => "LOAD_PATH",
+            Self::LoadLiteral(_) => "LOAD_LIT",
+            Self::Equals => "CMP_EQ",
+            Self::NotEquals => "CMP_NE",
+            Self::LesserThan => "CMP_LT",
+            Self::LesserThanEquals => "CMP_LE",
+            Self::GreaterThan => "CMP_GT",
+            Self::GreaterThanEquals => "CMP_GE",
+            Self::Has => "HAS",
+            Self::Call { .. } => "CALL",
+            Self::Test => "TEST",
+            Self::Not => "NOT",
+            Self::JumpIfFalse(_) => "JMP_FALSE",
+            Self::JumpIfTrue(_) => "JMP_TRUE",
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mnemonic = self.mnemonic();
+
+        match self {
+            Self::LoadPath(index) => write!(f, "{mnemonic} path[{index}]"),
+            Self::LoadLiteral(index) => write!(f, "{mnemonic} lit[{index}]"),
+            Self::Call { function, args } => write!(f, "{mnemonic} path[{function}] args={args}"),
+            Self::JumpIfFalse(address) | Self::JumpIfTrue(address) => {
+                write!(f, "{mnemonic} {address:04}")
+            }
+            _ => f.write_str(mnemonic),
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! Tree-walking interpreter of parsed filters, evaluating them as written.
+//!
+//! The interpreter neither normalizes, compiles nor optimizes filters, and implements the
+//! rules of the stack machine on its own rather than sharing its code, so that it can serve
+//! as a reference the compiled filters are checked against.
+//!
+//! Operators don't short-circuit: every operand is evaluated, an error raised by any of
+//! them failing the whole filter. A filter the interpreter evaluates successfully thus
+//! evaluates to the same result once compiled, whatever the order its operands end up in.
+//! Filters failing to evaluate can be interpreted leniently instead, to tell whether their
+//! compiled form can short-circuit the operands failing.
+
+use crate::{
+    error::EvalError,
+    record::{Record, Value},
+    registers::Literal,
+};
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use rapiere_parser::{self as parser, Arg, Comparable, Comparator, Expression, Member, Span};
+use std::cmp::Ordering;
+
+/// Whether the record matches the parsed filter, an empty filter matching every record.
+///
+/// Operands not being comparable are reported at address 0, the interpreter running no
+/// program.
+pub fn interpret(filter: &parser::Filter, record: &impl Record) -> Result<bool, EvalError> {
+    Interpreter {
+        record,
+        lenient: false,
+    }
+    .filter(filter)
+}
+
+/// Whether the record matches the parsed filter, operators being decided by any operand
+/// evaluated successfully, e.g. `a OR b` matching when `a` matches even though `b` fails.
+///
+/// Whatever the order a compiled filter short-circuits its operands in, it fails to
+/// evaluate when the lenient interpretation fails, and otherwise either fails or evaluates
+/// to the same result.
+pub fn interpret_leniently(
+    filter: &parser::Filter,
+    record: &impl Record,
+) -> Result<bool, EvalError> {
+    Interpreter {
+        record,
+        lenient: true,
+    }
+    .filter(filter)
+}
+
+/// Left-hand side and comparator distributed over the global restrictions of a composite
+/// argument.
+type Subject<'f> = (&'f Comparable, Comparator);
+
+struct Interpreter<'r, R> {
+    record: &'r R,
+
+    /// Whether operators are decided by the operands evaluated successfully
+    lenient: bool,
+}
+
+impl<R: Record> Interpreter<'_, R> {
+    fn filter(&self, filter: &parser::Filter) -> Result<bool, EvalError> {
+        let Some(expression) = &filter.expression else {
+            return Ok(true);
+        };
+
+        // Rejected as a whole, whichever operands the evaluation reaches
+        if let Some(span) = nested_comparison(expression, false) {
+            return Err(EvalError::NestedComparison { span });
+        }
+
+        self.expression(expression, None)
+    }
+
+    fn expression(
+        &self,
+        expression: &Expression,
+        subject: Option<Subject>,
+    ) -> Result<bool, EvalError> {
+        match expression {
+            Expression::And(operands) | Expression::Sequence(operands) => {
+                self.operands(operands, subject, false)
+            }
+            Expression::Or(operands) => self.operands(operands, subject, true),
+            Expression::Not(operand) => Ok(!self.expression(operand, subject)?),
+            Expression::Restriction(restriction) => match (&restriction.comparison, subject) {
+                (Some(_), Some(_)) => Err(EvalError::NestedComparison {
+                    span: restriction.span,
+                }),
+                (Some((comparator, Arg::Composite(expression))), None) => {
+                    self.expression(expression, Some((&restriction.comparable, *comparator)))
+                }
+                (Some((comparator, arg)), None) => {
+                    let lhs = self.operand(&restriction.comparable)?;
+                    compare(*comparator, &lhs, &self.arg(arg)?)
+                }
+                (None, Some((comparable, comparator))) => {
+                    let lhs = self.operand(comparable)?;
+                    let rhs = self.arg(&Arg::Comparable(restriction.comparable.clone()))?;
+                    compare(comparator, &lhs, &rhs)
+                }
+                (None, None) => Ok(truthy(&self.operand(&restriction.comparable)?)),
+            },
+            Expression::Error(_) => Err(EvalError::Unparsed),
+        }
+    }
+
+    /// Result of an operator, which an operand evaluating to the decisive value decides,
+    /// e.g. `false` for `AND`.
+    fn operands(
+        &self,
+        operands: &[Expression],
+        subject: Option<Subject>,
+        decisive: bool,
+    ) -> Result<bool, EvalError> {
+        let (mut decided, mut failure) = (false, None);
+        for operand in operands {
+            match self.expression(operand, subject) {
+                Ok(matches) => decided |= matches == decisive,
+                Err(err) if self.lenient => failure = failure.or(Some(err)),
+                Err(err) => return Err(err),
+            }
+        }
+
+        match failure {
+            Some(err) if !decided => Err(err),
+            // Decisive when decided, its negation otherwise
+            _ => Ok(decided == decisive),
+        }
+    }
+
+    /// Value of the left-hand side of a comparison, or of the argument of a function.
+    ///
+    /// Bare words and members are paths of the record, while other values are literals.
+    fn operand(&self, comparable: &Comparable) -> Result<Value, EvalError> {
+        match comparable {
+            Comparable::Member(Member { value, fields })
+                if fields.is_empty() && !matches!(value, parser::Value::Text(_)) =>
+            {
+                Ok(Value::Literal(literal(value)))
+            }
+            Comparable::Member(member) => Ok(self.record.get(&path(member)).into_owned()),
+            Comparable::Function(function) => {
+                let args = function
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        Arg::Comparable(comparable) => self.operand(comparable),
+                        Arg::Composite(expression) => self
+                            .expression(expression, None)
+                            .map(|matches| Value::Literal(Literal::Boolean(matches))),
+                        Arg::Error(_) => Err(EvalError::Unparsed),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.record.call(&function.name, &args)
+            }
+        }
+    }
+
+    /// Value of the right-hand side of a comparison.
+    ///
+    /// Bare words are enum values and members strings of their path, while functions are
+    /// called.
+    fn arg(&self, arg: &Arg) -> Result<Value, EvalError> {
+        match arg {
+            Arg::Comparable(Comparable::Member(member)) if member.fields.is_empty() => {
+                Ok(Value::Literal(literal(&member.value)))
+            }
+            Arg::Comparable(Comparable::Member(member)) => {
+                Ok(Value::Literal(Literal::String(path(member))))
+            }
+            Arg::Comparable(comparable) => self.operand(comparable),
+            Arg::Composite(expression) => self
+                .expression(expression, None)
+                .map(|matches| Value::Literal(Literal::Boolean(matches))),
+            Arg::Error(_) => Err(EvalError::Unparsed),
+        }
+    }
+}
+
+/// Span of the first comparison within a composite argument, which only holds the values
+/// its comparison applies to, `nested` telling whether the expression is one.
+///
+/// The composite arguments of functions are expressions of their own, which may hold
+/// comparisons.
+fn nested_comparison(expression: &Expression, nested: bool) -> Option<Span> {
+    match expression {
+        Expression::And(operands) | Expression::Or(operands) | Expression::Sequence(operands) => {
+            operands
+                .iter()
+                .find_map(|operand| nested_comparison(operand, nested))
+        }
+        Expression::Not(operand) => nested_comparison(operand, nested),
+        Expression::Restriction(restriction) => match &restriction.comparison {
+            Some(_) if nested => Some(restriction.span),
+            Some((_, arg)) => nested_comparison_in(&restriction.comparable).or_else(|| match arg {
+                Arg::Composite(expression) => nested_comparison(expression, true),
+                Arg::Comparable(comparable) => nested_comparison_in(comparable),
+                Arg::Error(_) => None,
+            }),
+            None => nested_comparison_in(&restriction.comparable),
+        },
+        Expression::Error(_) => None,
+    }
+}
+
+/// Span of the first comparison within a composite argument of a function's arguments.
+fn nested_comparison_in(comparable: &Comparable) -> Option<Span> {
+    match comparable {
+        Comparable::Function(function) => function.args.iter().find_map(|arg| match arg {
+            Arg::Composite(expression) => nested_comparison(expression, false),
+            Arg::Comparable(comparable) => nested_comparison_in(comparable),
+            Arg::Error(_) => None,
+        }),
+        Comparable::Member(_) => None,
+    }
+}
+
+/// Dot-joined path of a member, its root being written unquoted.
+fn path(member: &Member) -> String {
+    let mut path = match &member.value {
+        parser::Value::Text(root) | parser::Value::String(root) => root.clone(),
+        root => root.to_string(),
+    };
+    for field in &member.fields {
+        path.push('.');
+        path.push_str(field);
+    }
+
+    path
+}
+
+/// Literal of a parsed value, strings holding timestamps or durations being parsed.
+fn literal(value: &parser::Value) -> Literal {
+    match value {
+        parser::Value::Text(name) => Literal::EnumValue(name.clone()),
+        parser::Value::String(text) => timestamp(text)
+            .map(Literal::Timestamp)
+            .or_else(|| duration(text).map(Literal::Duration))
+            .unwrap_or_else(|| Literal::String(text.clone())),
+        parser::Value::Integer(value) => Literal::Integer(*value),
+        parser::Value::Float(value) => Literal::Float(*value),
+        parser::Value::Boolean(value) => Literal::Boolean(*value),
+        parser::Value::Null => Literal::Null,
+    }
+}
+
+fn timestamp(text: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(text).ok()
+}
+
+/// Duration of a decimal number of seconds, e.g. `-1.25s`, with at most nine decimals.
+fn duration(text: &str) -> Option<TimeDelta> {
+    let text = text.strip_suffix('s')?;
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text),
+    };
+    let (seconds, decimals) = text.split_once('.').unwrap_or((text, ""));
+
+    let is_number = |text: &str| text.chars().all(|char| char.is_ascii_digit());
+    if seconds.is_empty() || decimals.len() > 9 || !is_number(seconds) || !is_number(decimals) {
+        return None;
+    }
+
+    // Decimals padded to nanoseconds, e.g. `25` to `250000000`
+    let nanos = format!("{decimals:0<9}").parse().ok()?;
+    let duration = TimeDelta::new(seconds.parse().ok()?, nanos)?;
+
+    Some(if negative { -duration } else { duration })
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Literal(Literal::Boolean(value)) => *value,
+        Value::Literal(Literal::Bytes(value)) => !value.is_empty(),
+        Value::Literal(Literal::Duration(value)) => *value != TimeDelta::zero(),
+        Value::Literal(Literal::EnumValue(value) | Literal::String(value)) => !value.is_empty(),
+        Value::Literal(Literal::Float(value)) => *value != 0.0,
+        Value::Literal(Literal::Integer(value)) => *value != 0,
+        Value::Literal(Literal::Timestamp(_)) => true,
+        Value::Literal(Literal::Null) | Value::Missing => false,
+        Value::List(values) => !values.is_empty(),
+        Value::Map(entries) => !entries.is_empty(),
+    }
+}
+
+fn compare(comparator: Comparator, lhs: &Value, rhs: &Value) -> Result<bool, EvalError> {
+    let not_comparable = || EvalError::NotComparable { address: 0 };
+    let rhs = scalar(rhs).ok_or_else(not_comparable)?;
+    if comparator == Comparator::Has {
+        return Ok(has(lhs, rhs));
+    }
+
+    let lhs = scalar(lhs).ok_or_else(not_comparable)?;
+    let ordering = order(lhs, rhs);
+
+    Ok(match comparator {
+        Comparator::Equals => equal(lhs, rhs),
+        Comparator::NotEquals => !equal(lhs, rhs),
+        Comparator::LesserThan => ordering == Some(Ordering::Less),
+        Comparator::LesserThanEquals => ordering.is_some_and(Ordering::is_le),
+        Comparator::GreaterThan => ordering == Some(Ordering::Greater),
+        Comparator::GreaterThanEquals => ordering.is_some_and(Ordering::is_ge),
+        Comparator::Has => unreachable!("`:` is applied to any value"),
+    })
+}
+
+/// Literal of a scalar value, a missing value being `null`.
+fn scalar(value: &Value) -> Option<&Literal> {
+    match value {
+        Value::Literal(literal) => Some(literal),
+        Value::Missing => Some(&Literal::Null),
+        Value::List(_) | Value::Map(_) => None,
+    }
+}
+
+/// Whether the values are equal, enum values and strings comparing by their text, and a
+/// string holding `*` matching as a pattern.
+fn equal(lhs: &Literal, rhs: &Literal) -> bool {
+    match (lhs, rhs) {
+        (Literal::String(text) | Literal::EnumValue(text), Literal::String(pattern))
+            if pattern.contains('*') =>
+        {
+            glob(pattern, text)
+        }
+        (
+            Literal::String(lhs) | Literal::EnumValue(lhs),
+            Literal::String(rhs) | Literal::EnumValue(rhs),
+        ) => lhs == rhs,
+        _ => order(lhs, rhs) == Some(Ordering::Equal),
+    }
+}
+
+/// Order of values of the same type, strings being parsed when compared with timestamps
+/// and durations, and compared as bytes with bytes.
+fn order(lhs: &Literal, rhs: &Literal) -> Option<Ordering> {
+    use Literal::*;
+
+    match (lhs, rhs) {
+        (Integer(lhs), Integer(rhs)) => Some(lhs.cmp(rhs)),
+        (Integer(_) | Float(_), Integer(_) | Float(_)) => number(lhs)?.partial_cmp(&number(rhs)?),
+        (String(lhs), String(rhs)) => Some(lhs.cmp(rhs)),
+        (Boolean(lhs), Boolean(rhs)) => Some(lhs.cmp(rhs)),
+        (Timestamp(_) | String(_), Timestamp(_) | String(_)) => {
+            Some(instant(lhs)?.cmp(&instant(rhs)?))
+        }
+        (Duration(_) | String(_), Duration(_) | String(_)) => Some(span(lhs)?.cmp(&span(rhs)?)),
+        (Bytes(_) | String(_), Bytes(_) | String(_)) => Some(bytes(lhs)?.cmp(bytes(rhs)?)),
+        (Null, Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn number(literal: &Literal) -> Option<f64> {
+    match literal {
+        Literal::Integer(value) => Some(*value as f64),
+        Literal::Float(value) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+fn instant(literal: &Literal) -> Option<DateTime<FixedOffset>> {
+    match literal {
+        Literal::Timestamp(value) => Some(*value),
+        Literal::String(text) => timestamp(text),
+        _ => None,
+    }
+}
+
+fn span(literal: &Literal) -> Option<TimeDelta> {
+    match literal {
+        Literal::Duration(value) => Some(*value),
+        Literal::String(text) => duration(text),
+        _ => None,
+    }
+}
+
+fn bytes(literal: &Literal) -> Option<&[u8]> {
+    match literal {
+        Literal::Bytes(value) => Some(value),
+        Literal::String(text) => Some(text.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Whether the value has the argument, as `:` does: any present value has `*`, a list has
+/// the arguments of its elements, a map its keys, and a scalar the values it equals.
+fn has(value: &Value, arg: &Literal) -> bool {
+    let any = matches!(arg, Literal::String(pattern) if pattern == "*");
+
+    match value {
+        Value::Missing | Value::Literal(Literal::Null) => false,
+        Value::Literal(literal) => any || equal(literal, arg),
+        Value::List(values) => any || values.iter().any(|value| has(value, arg)),
+        Value::Map(entries) => match arg {
+            Literal::String(key) | Literal::EnumValue(key) => any || entries.contains_key(key),
+            _ => false,
+        },
+    }
+}
+
+/// Whether a text matches a pattern in which `*` matches any text: the text must start
+/// with what precedes the first `*`, end with what follows the last one, and hold what
+/// separates them in order.
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let Some(last) = parts.next_back() else {
+        return rest.is_empty();
+    };
+    let Some(middle) = rest.strip_suffix(last) else {
+        return false;
+    };
+    rest = middle;
+
+    for part in parts {
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::compile;
+    use rapiere_parser::parse;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
+
+    fn record() -> Value {
+        let string = |value: &str| Value::Literal(Literal::String(value.to_owned()));
+
+        Value::Map(BTreeMap::from([
+            ("name".to_owned(), string("rapiere")),
+            ("size".to_owned(), Value::Literal(Literal::Integer(42))),
+            ("public".to_owned(), Value::Literal(Literal::Boolean(true))),
+            ("state".to_owned(), string("ACTIVE")),
+            (
+                "tags".to_owned(),
+                Value::List(vec![string("rust"), string("filter")]),
+            ),
+            (
+                "owner".to_owned(),
+                Value::Map(BTreeMap::from([("name".to_owned(), string("ada"))])),
+            ),
+        ]))
+    }
+
+    #[rstest]
+    #[case::empty(b"", true)]
+    #[case::equals(b"name = \"rapiere\"", true)]
+    #[case::wildcard(b"name = \"rap*\"", true)]
+    #[case::ordering(b"size > 40 AND size <= 42", true)]
+    #[case::enum_value(b"state = ACTIVE", true)]
+    #[case::has_element(b"tags:rust", true)]
+    #[case::has_key(b"owner:name", true)]
+    #[case::nested(b"owner.name = \"ada\"", true)]
+    #[case::composite(b"size = (1 OR 42)", true)]
+    #[case::negated_composite(b"size = (1 OR NOT 42)", false)]
+    #[case::global(b"public AND NOT missing", true)]
+    #[case::missing(b"missing = null", true)]
+    #[case::or(b"size < 10 OR name:\"*\"", true)]
+    #[case::not(b"NOT size = 42", false)]
+    fn it_interprets_a_filter(#[case] input: &[u8], #[case] expected: bool) {
+        let filter = parse(input).unwrap();
+
+        assert_eq!(interpret(&filter, &record()).unwrap(), expected);
+        assert_eq!(
+            compile(filter).unwrap().matches(&record()).unwrap(),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::list(b"tags = \"rust\"")]
+    #[case::map(b"owner < 1")]
+    #[case::unknown_function(b"size(tags) = 2")]
+    // Operators don't short-circuit
+    #[case::short_circuit(b"public OR tags = \"rust\"")]
+    fn it_fails_to_interpret(#[case] input: &[u8]) {
+        let filter = parse(input).unwrap();
+
+        assert!(interpret(&filter, &record()).is_err());
+    }
+
+    #[rstest]
+    #[case::comparison(b"size = (name > 1)", 8)]
+    #[case::composite(b"size = (name = (1 OR 2))", 8)]
+    #[case::decided(b"public OR size = (1 OR name:\"*\")", 23)]
+    #[case::function_arg(b"size(((tags = (name > 1)))) = 2", 15)]
+    fn it_rejects_a_nested_comparison(#[case] input: &[u8], #[case] offset: usize) {
+        let filter = parse(input).unwrap();
+
+        for result in [
+            interpret(&filter, &record()),
+            interpret_leniently(&filter, &record()),
+        ] {
+            assert!(
+                matches!(result, Err(EvalError::NestedComparison { span }) if span.offset == offset),
+                "{result:?}"
+            );
+        }
+        assert!(matches!(
+            compile(filter),
+            Err(crate::Error::NestedComparison { span }) if span.offset == offset
+        ));
+    }
+
+    #[rstest]
+    #[case::decided_or(b"public OR tags = \"rust\"", Some(true))]
+    #[case::decided_and(b"NOT public AND tags = \"rust\"", Some(false))]
+    #[case::negated(b"NOT (tags = \"rust\" OR public)", Some(false))]
+    #[case::undecided_or(b"NOT public OR tags = \"rust\"", None)]
+    #[case::undecided_and(b"public AND tags = \"rust\"", None)]
+    #[case::composite(b"NOT public OR tags = (\"rust\" OR \"go\")", None)]
+    #[case::function(b"size(tags) = 2 OR size = 42", Some(true))]
+    #[case::matches(b"size = 42", Some(true))]
+    fn it_interprets_leniently(#[case] input: &[u8], #[case] expected: Option<bool>) {
+        let filter = parse(input).unwrap();
+
+        assert_eq!(interpret_leniently(&filter, &record()).ok(), expected);
+    }
+
+    #[rstest]
+    #[case("", "", true)]
+    #[case("*", "anything", true)]
+    #[case("a*c", "abbbc", true)]
+    #[case("a*c", "abbbd", false)]
+    #[case("*a*b", "xaxxb", true)]
+    #[case("**", "", true)]
+    #[case("a", "ab", false)]
+    #[case("ab*ba", "aba", false)]
+    #[case("a*b*a", "aba", true)]
+    #[case("a*b*a", "aab", false)]
+    #[case("*é", "café", true)]
+    fn it_matches_patterns(#[case] pattern: &str, #[case] text: &str, #[case] expected: bool) {
+        assert_eq!(glob(pattern, text), expected);
+    }
+
+    #[rstest]
+    #[case::seconds("30s", Some(TimeDelta::seconds(30)))]
+    #[case::decimals("-1.25s", Some(-TimeDelta::milliseconds(1250)))]
+    #[case::trailing_point("2.s", Some(TimeDelta::seconds(2)))]
+    #[case::nanoseconds("0.000000001s", Some(TimeDelta::nanoseconds(1)))]
+    #[case::too_precise("0.0000000001s", None)]
+    #[case::no_seconds(".5s", None)]
+    #[case::sign("+1s", None)]
+    #[case::unit("1m", None)]
+    fn it_parses_durations(#[case] text: &str, #[case] expected: Option<TimeDelta>) {
+        assert_eq!(duration(text), expected);
+    }
+}
@@ -0,0 +1,230 @@
+//! Records backed by JSON documents.
+//!
+//! Values are resolved as follows:
+//! - objects are traversed by their keys, and arrays by resolving the path from each of
+//!   their elements, e.g. `a.b` resolving the `b` of every element of the `a` array;
+//! - numbers fitting in an `i64` are integers, and floats otherwise, narrowed to the `f32`
+//!   of [`Literal::Float`]: they lose precision beyond 24 bits of mantissa, and those out of
+//!   its range become infinite, e.g. `1e39`;
+//! - strings stay strings, comparisons parsing them when compared with timestamps or
+//!   durations, e.g. `"2024-01-01T00:00:00Z"` or `"30s"`;
+//! - keys the document does not hold are missing, while `null` ones are `null`, both
+//!   comparing as `null`.
+//!
+//! Documents can't be borrowed as values, so the value a path resolves to is converted,
+//! copying the strings it holds, and the whole subtree of an array or an object, e.g. when
+//! it is tested or passed to a function. Applying `:` to a path copies nothing but the
+//! scalars it compares, arrays and objects being walked in place.
+
+use crate::{
+    record::{Record, Value},
+    registers::Literal,
+    vm,
+};
+use serde_json::{Map, Value as Json};
+use std::borrow::Cow;
+
+impl From<&Json> for Value {
+    fn from(json: &Json) -> Self {
+        match json {
+            Json::Null => Value::Literal(Literal::Null),
+            Json::Bool(value) => Value::Literal(Literal::Boolean(*value)),
+            Json::Number(number) => Value::Literal(match number.as_i64() {
+                Some(value) => Literal::Integer(value),
+                None => Literal::Float(number.as_f64().unwrap_or(f64::NAN) as f32),
+            }),
+            Json::String(value) => Value::Literal(Literal::String(value.clone())),
+            Json::Array(values) => Value::List(values.iter().map(Value::from).collect()),
+            Json::Object(entries) => Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// A document is the root of the record, e.g. an object holding its top-level fields.
+impl Record for Json {
+    #[inline]
+    fn get(&self, path: &str) -> Cow<'_, Value> {
+        Cow::Owned(resolve(self, path))
+    }
+
+    #[inline]
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        has(self, path, arg)
+    }
+}
+
+impl Record for Map<String, Json> {
+    #[inline]
+    fn get(&self, path: &str) -> Cow<'_, Value> {
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+
+        Cow::Owned(Map::get(self, key).map_or(Value::Missing, |json| resolve(json, rest)))
+    }
+
+    #[inline]
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+
+        Map::get(self, key).is_some_and(|json| has(json, rest, arg))
+    }
+}
+
+/// Value of a dot-joined path of a document, mirroring [`Value::get`].
+fn resolve(json: &Json, path: &str) -> Value {
+    if path.is_empty() {
+        return Value::from(json);
+    }
+
+    let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+    match json {
+        Json::Object(entries) => entries
+            .get(key)
+            .map_or(Value::Missing, |json| resolve(json, rest)),
+        Json::Array(values) => Value::List(
+            values
+                .iter()
+                .map(|json| resolve(json, path))
+                .filter(|value| *value != Value::Missing)
+                .collect(),
+        ),
+        _ => Value::Missing,
+    }
+}
+
+/// Whether the value of a dot-joined path of a document has the argument, mirroring `:`
+/// without converting the arrays and objects walked.
+fn has(json: &Json, path: &str, arg: &Literal) -> bool {
+    let any = matches!(arg, Literal::String(pattern) if pattern == "*");
+
+    if path.is_empty() {
+        return match json {
+            Json::Array(values) => any || values.iter().any(|json| has(json, path, arg)),
+            Json::Object(entries) => match arg {
+                Literal::String(key) | Literal::EnumValue(key) => any || entries.contains_key(key),
+                _ => false,
+            },
+            scalar => vm::has(&Value::from(scalar), arg),
+        };
+    }
+
+    let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+    match json {
+        Json::Object(entries) => entries.get(key).is_some_and(|json| has(json, rest, arg)),
+        // Any array has `*`, even when none of its elements holds the path
+        Json::Array(_) if any => true,
+        Json::Array(values) => values.iter().any(|json| has(json, path, arg)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::compile;
+    use rapiere_parser::parse;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn document() -> Json {
+        json!({
+            "name": "rapiere",
+            "size": 42,
+            "ratio": 1.5,
+            "public": true,
+            "owner": null,
+            "state": "ACTIVE",
+            "create_time": "2024-06-01T12:00:00+02:00",
+            "timeout": "30s",
+            "tags": ["rust", "filter"],
+            "labels": { "env": "prod", "team": "core" },
+            "versions": [{ "major": 1 }, { "major": 2, "minor": 3 }],
+        })
+    }
+
+    #[rstest]
+    #[case::string(b"name = \"rapiere\"", true)]
+    #[case::wildcard(b"name = \"rap*\"", true)]
+    #[case::integer(b"size = 42 AND size > 41.5", true)]
+    #[case::float(b"ratio > 1 AND ratio < 2 AND ratio = 1.5", true)]
+    #[case::boolean(b"public = true AND public", true)]
+    #[case::enum_value(b"state = ACTIVE", true)]
+    #[case::timestamp(b"create_time = \"2024-06-01T10:00:00Z\"", true)]
+    #[case::timestamp_order(b"create_time < \"2024-06-01T11:00:00Z\"", true)]
+    #[case::duration(b"timeout = \"30.0s\" AND timeout > \"20s\"", true)]
+    #[case::member(b"labels.env = \"prod\"", true)]
+    #[case::array_element(b"tags:rust AND NOT tags:go", true)]
+    #[case::array_any(b"tags:\"*\"", true)]
+    #[case::object_key(b"labels:team AND NOT labels:owner", true)]
+    #[case::array_members(b"versions.major:2 AND versions.minor:3", true)]
+    #[case::missing_array_members(b"versions.patch OR versions.minor:1", false)]
+    #[case::null(b"owner = null AND NOT owner", true)]
+    #[case::missing(b"missing = null AND NOT missing AND NOT missing:\"*\"", true)]
+    #[case::missing_member(b"labels.missing = null AND name.missing = null", true)]
+    fn it_matches_a_document(#[case] input: &[u8], #[case] expected: bool) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+
+        assert_eq!(filter.matches(&document()).unwrap(), expected);
+
+        let Json::Object(entries) = document() else {
+            unreachable!()
+        };
+        assert_eq!(filter.matches(&entries).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::key("labels", "team")]
+    #[case::member("labels.env", "prod")]
+    #[case::element("tags", "filter")]
+    #[case::element_pattern("tags", "ru*")]
+    #[case::present("tags", "*")]
+    #[case::number("size", "*")]
+    #[case::null("owner", "*")]
+    #[case::traversal("versions.major", "2")]
+    #[case::traversal_wildcard("versions.patch", "*")]
+    #[case::traversal_missing("versions.patch", "1")]
+    #[case::scalar_member("name.missing", "*")]
+    #[case::missing("missing", "*")]
+    fn it_walks_a_document_lazily(#[case] path: &str, #[case] arg: &str) {
+        let document = document();
+        let arg = match arg.parse() {
+            Ok(value) => Literal::Integer(value),
+            Err(_) => Literal::String(arg.to_owned()),
+        };
+
+        assert_eq!(
+            Record::has(&document, path, &arg),
+            vm::has(&Record::get(&document, path), &arg)
+        );
+    }
+
+    #[rstest]
+    #[case::array(b"tags = \"rust\"")]
+    #[case::object(b"labels < 1")]
+    fn it_fails_to_compare_a_document(#[case] input: &[u8]) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+
+        assert!(filter.matches(&document()).is_err());
+    }
+
+    #[rstest]
+    #[case::null(json!(null), Value::Literal(Literal::Null))]
+    #[case::integer(json!(-7), Value::Literal(Literal::Integer(-7)))]
+    #[case::float(json!(0.25), Value::Literal(Literal::Float(0.25)))]
+    #[case::large_integer(json!(u64::MAX), Value::Literal(Literal::Float(u64::MAX as f32)))]
+    #[case::out_of_range(json!(1e39), Value::Literal(Literal::Float(f32::INFINITY)))]
+    #[case::array(
+        json!([true, "a"]),
+        Value::List(vec![
+            Value::Literal(Literal::Boolean(true)),
+            Value::Literal(Literal::String("a".to_owned())),
+        ])
+    )]
+    fn it_converts_a_document(#[case] json: Json, #[case] expected: Value) {
+        assert_eq!(Value::from(&json), expected);
+    }
+}
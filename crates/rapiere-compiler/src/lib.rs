@@ -1,2 +1,27 @@
+mod compile;
+mod disassemble;
+mod encoding;
+mod error;
 mod filter;
+mod instruction;
+mod interpret;
+#[cfg(feature = "json")]
+mod json;
+mod normalize;
+mod optimize;
+mod record;
 mod registers;
+mod vm;
+
+pub use compile::{compile, Compiler};
+pub use disassemble::Disassembly;
+pub use encoding::DecodeLimits;
+pub use error::{DecodeError, Error, EvalError, RegisterError};
+pub use filter::Filter;
+pub use instruction::{Address, Instruction};
+pub use interpret::{interpret, interpret_leniently};
+pub use normalize::{normalize, NormalForm, Normalization};
+pub use optimize::{Optimizer, Pass};
+pub use record::{Record, Value};
+pub use registers::{Index, IndexWidth, Literal, LiteralRegister, PathRegister, Register};
+pub use vm::EvalContext;
@@ -0,0 +1,416 @@
+//! Rewriting of parsed filters into a canonical logical form.
+//!
+//! Normalization runs the following passes, in order:
+//! - sequences are turned into conjunctions, AIP-160 giving them the same meaning, and
+//!   nested conjunctions and disjunctions are flattened;
+//! - negations are pushed down to restrictions with De Morgan's laws, `NOT NOT a` becoming
+//!   `a`;
+//! - `true` and `false` constants are folded, e.g. `true AND a` becoming `a`;
+//! - the expression is optionally converted into its conjunctive or disjunctive normal
+//!   form;
+//! - operands of conjunctions and disjunctions are deduplicated, then sorted by their
+//!   canonical text.
+
+use crate::error::Error;
+use rapiere_parser::{Comparable, Expression, Filter, Limits, Member, Restriction, Span, Value};
+
+/// Constructor of an expression joining operands.
+type Operator = fn(Vec<Expression>) -> Expression;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NormalForm {
+    /// Conjunction of disjunctions, e.g. `(a OR b) AND c`
+    Conjunctive,
+
+    /// Disjunction of conjunctions, e.g. `(a AND b) OR c`
+    Disjunctive,
+}
+
+#[derive(Clone, Debug)]
+pub struct Normalization {
+    form: Option<NormalForm>,
+    limits: Limits,
+    max_clauses: usize,
+}
+
+impl Normalization {
+    pub const DEFAULT_MAX_CLAUSES: usize = 256;
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Converts normalized filters into the given normal form.
+    #[inline]
+    pub fn with_form(mut self, form: NormalForm) -> Self {
+        self.form = Some(form);
+        self
+    }
+
+    /// Caps the number of clauses of a normal form, which grows exponentially with the
+    /// number of operands distributed over each other.
+    #[inline]
+    pub fn with_max_clauses(mut self, max_clauses: usize) -> Self {
+        self.max_clauses = max_clauses;
+        self
+    }
+
+    /// Bounds the filters to normalize, which may not come from the parser.
+    #[inline]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn normalize(&self, filter: Filter) -> Result<Filter, Error> {
+        self.limits.check(&filter)?;
+
+        let Some(expression) = filter.expression else {
+            return Ok(Filter::new(None));
+        };
+
+        let expression = flatten(expression);
+        let expression = push_negations(expression, false);
+        let expression = fold(expression);
+        let expression = match self.form {
+            Some(form) => self.convert(expression, form)?,
+            None => expression,
+        };
+
+        Ok(Filter::new(Some(sort(expression))))
+    }
+
+    fn convert(&self, expression: Expression, form: NormalForm) -> Result<Expression, Error> {
+        let (outer, inner): (Operator, Operator) = match form {
+            NormalForm::Conjunctive => (Expression::And, Expression::Or),
+            NormalForm::Disjunctive => (Expression::Or, Expression::And),
+        };
+
+        let clauses = self
+            .clauses(expression, form)?
+            .into_iter()
+            .map(|clause| join(clause, inner))
+            .collect();
+
+        Ok(join(clauses, outer))
+    }
+
+    /// Splits a flattened expression in negation normal form into clauses, the operands
+    /// of the normal form's outer operator.
+    fn clauses(
+        &self,
+        expression: Expression,
+        form: NormalForm,
+    ) -> Result<Vec<Vec<Expression>>, Error> {
+        match (expression, form) {
+            (Expression::And(operands), NormalForm::Conjunctive)
+            | (Expression::Or(operands), NormalForm::Disjunctive) => {
+                let mut clauses = Vec::new();
+
+                for operand in operands {
+                    clauses.extend(self.clauses(operand, form)?);
+                    self.check(clauses.len())?;
+                }
+
+                Ok(clauses)
+            }
+            (Expression::Or(operands), NormalForm::Conjunctive)
+            | (Expression::And(operands), NormalForm::Disjunctive) => {
+                let mut clauses = vec![Vec::new()];
+
+                for operand in operands {
+                    let distributed = self.clauses(operand, form)?;
+                    self.check(clauses.len() * distributed.len())?;
+
+                    clauses = clauses
+                        .iter()
+                        .flat_map(|clause: &Vec<Expression>| {
+                            distributed.iter().map(move |other| {
+                                clause.iter().chain(other).cloned().collect::<Vec<_>>()
+                            })
+                        })
+                        .collect();
+                }
+
+                Ok(clauses)
+            }
+            (expression, _) => Ok(vec![vec![expression]]),
+        }
+    }
+
+    #[inline]
+    fn check(&self, clauses: usize) -> Result<(), Error> {
+        if clauses > self.max_clauses {
+            return Err(Error::NormalFormTooLarge {
+                clauses,
+                max: self.max_clauses,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Normalization {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            form: None,
+            limits: Limits::default(),
+            max_clauses: Self::DEFAULT_MAX_CLAUSES,
+        }
+    }
+}
+
+/// Normalizes a filter with the default passes, without converting it into a normal form
+/// nor bounding the filter.
+#[inline(always)]
+pub fn normalize(filter: Filter) -> Filter {
+    Normalization::new()
+        .with_limits(Limits::unlimited())
+        .normalize(filter)
+        .expect("normalization can only fail when converting into a normal form")
+}
+
+fn flatten(expression: Expression) -> Expression {
+    match expression {
+        Expression::And(operands) | Expression::Sequence(operands) => {
+            let mut flattened = Vec::with_capacity(operands.len());
+
+            for operand in operands.into_iter().map(flatten) {
+                match operand {
+                    Expression::And(operands) => flattened.extend(operands),
+                    operand => flattened.push(operand),
+                }
+            }
+
+            join(flattened, Expression::And)
+        }
+        Expression::Or(operands) => {
+            let mut flattened = Vec::with_capacity(operands.len());
+
+            for operand in operands.into_iter().map(flatten) {
+                match operand {
+                    Expression::Or(operands) => flattened.extend(operands),
+                    operand => flattened.push(operand),
+                }
+            }
+
+            join(flattened, Expression::Or)
+        }
+        Expression::Not(operand) => Expression::Not(Box::new(flatten(*operand))),
+        expression => expression,
+    }
+}
+
+/// Pushes negations down to restrictions, `negated` telling whether the expression is
+/// under an odd number of negations.
+fn push_negations(expression: Expression, negated: bool) -> Expression {
+    match expression {
+        Expression::And(operands) | Expression::Sequence(operands) => {
+            let operands = operands
+                .into_iter()
+                .map(|operand| push_negations(operand, negated))
+                .collect();
+
+            if negated {
+                flatten(Expression::Or(operands))
+            } else {
+                flatten(Expression::And(operands))
+            }
+        }
+        Expression::Or(operands) => {
+            let operands = operands
+                .into_iter()
+                .map(|operand| push_negations(operand, negated))
+                .collect();
+
+            if negated {
+                flatten(Expression::And(operands))
+            } else {
+                flatten(Expression::Or(operands))
+            }
+        }
+        Expression::Not(operand) => push_negations(*operand, !negated),
+        expression => match constant(&expression) {
+            Some(value) => boolean(value != negated),
+            None if negated => Expression::Not(Box::new(expression)),
+            None => expression,
+        },
+    }
+}
+
+fn fold(expression: Expression) -> Expression {
+    match expression {
+        Expression::And(operands) => fold_operands(operands, false, Expression::And),
+        Expression::Or(operands) => fold_operands(operands, true, Expression::Or),
+        expression => expression,
+    }
+}
+
+/// Folds the operands of an operator for which `absorbing` is the absorbing constant, its
+/// negation being the identity one.
+fn fold_operands(operands: Vec<Expression>, absorbing: bool, operator: Operator) -> Expression {
+    let mut folded = Vec::with_capacity(operands.len());
+
+    for operand in operands.into_iter().map(fold) {
+        match constant(&operand) {
+            Some(value) if value == absorbing => return boolean(absorbing),
+            Some(_) => continue,
+            None => folded.push(operand),
+        }
+    }
+
+    if folded.is_empty() {
+        boolean(!absorbing)
+    } else {
+        flatten(join(folded, operator))
+    }
+}
+
+fn sort(expression: Expression) -> Expression {
+    let sort_operands = |operands: Vec<Expression>| {
+        let mut operands = operands.into_iter().map(sort).collect::<Vec<_>>();
+        operands.sort_by_cached_key(|operand| operand.to_string());
+        operands.dedup();
+
+        operands
+    };
+
+    match expression {
+        Expression::And(operands) => join(sort_operands(operands), Expression::And),
+        Expression::Or(operands) => join(sort_operands(operands), Expression::Or),
+        Expression::Not(operand) => Expression::Not(Box::new(sort(*operand))),
+        expression => expression,
+    }
+}
+
+/// Value of a global restriction on a boolean literal, e.g. `true`.
+#[inline]
+fn constant(expression: &Expression) -> Option<bool> {
+    match expression {
+        Expression::Restriction(Restriction {
+            comparable:
+                Comparable::Member(Member {
+                    value: Value::Boolean(value),
+                    fields,
+                }),
+            comparison: None,
+            ..
+        }) if fields.is_empty() => Some(*value),
+        _ => None,
+    }
+}
+
+#[inline]
+fn boolean(value: bool) -> Expression {
+    Expression::Restriction(Restriction::new(
+        Span::default(),
+        Comparable::Member(Member::new(Value::Boolean(value), Vec::new())),
+        None,
+    ))
+}
+
+#[inline]
+fn join(mut operands: Vec<Expression>, operator: Operator) -> Expression {
+    if operands.len() == 1 {
+        operands.pop().expect("operands hold a single expression")
+    } else {
+        operator(operands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapiere_parser::parse;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(b"", "")]
+    #[case::flattening(b"a AND (b AND (c d))", "a AND b AND c AND d")]
+    #[case::nested_disjunction(b"(a OR b) OR (c OR d)", "a OR b OR c OR d")]
+    #[case::double_negation(b"NOT x AND NOT (NOT b)", "NOT x AND b")]
+    #[case::de_morgan_conjunction(b"NOT (a AND b)", "NOT a OR NOT b")]
+    #[case::de_morgan_disjunction(b"-(a OR b = 1)", "NOT a AND NOT b = 1")]
+    #[case::de_morgan_sequence(b"NOT (a (b OR c))", "NOT a OR (NOT b AND NOT c)")]
+    #[case::true_conjunction(b"true AND a", "a")]
+    #[case::false_conjunction(b"a AND (false b)", "false")]
+    #[case::true_disjunction(b"a OR NOT false", "true")]
+    #[case::false_disjunction(b"a OR false OR b", "a OR b")]
+    #[case::fold_to_identity(b"true AND NOT false", "true")]
+    #[case::sorting(b"c = 1 AND b AND a > 2", "a > 2 AND b AND c = 1")]
+    #[case::deduplication(b"b OR a OR b", "a OR b")]
+    #[case::nested_sorting(b"(d OR c) AND (b OR a)", "a OR b AND c OR d")]
+    fn it_normalizes_a_filter(#[case] input: &[u8], #[case] expected: &str) {
+        let filter = normalize(parse(input).unwrap());
+
+        assert_eq!(filter.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::conjunctive(NormalForm::Conjunctive, b"a OR (b AND c)", "a OR b AND a OR c")]
+    #[case::conjunctive_noop(NormalForm::Conjunctive, b"(a OR b) AND c", "a OR b AND c")]
+    #[case::disjunctive(
+        NormalForm::Disjunctive,
+        b"(a OR b) AND (c OR d)",
+        "(a AND c) OR (a AND d) OR (b AND c) OR (b AND d)"
+    )]
+    #[case::disjunctive_negation(
+        NormalForm::Disjunctive,
+        b"NOT (a OR b) OR c",
+        "(NOT a AND NOT b) OR c"
+    )]
+    fn it_converts_a_filter_into_a_normal_form(
+        #[case] form: NormalForm,
+        #[case] input: &[u8],
+        #[case] expected: &str,
+    ) {
+        let filter = Normalization::new()
+            .with_form(form)
+            .normalize(parse(input).unwrap());
+
+        assert!(filter.is_ok(), "{}", filter.unwrap_err());
+        assert_eq!(filter.unwrap().to_string(), expected);
+    }
+
+    #[test]
+    fn it_guards_the_normal_form_size() {
+        let input = (0..8)
+            .map(|idx| format!("(a{} OR b{})", "x".repeat(idx), "x".repeat(idx)))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let normalization = Normalization::new()
+            .with_form(NormalForm::Disjunctive)
+            .with_max_clauses(200);
+        let filter = normalization.normalize(parse(input.as_bytes()).unwrap());
+
+        assert!(matches!(
+            filter,
+            Err(Error::NormalFormTooLarge {
+                clauses: 256,
+                max: 200
+            })
+        ));
+        assert!(normalization
+            .with_max_clauses(256)
+            .normalize(parse(input.as_bytes()).unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn it_enforces_limits() {
+        let normalization =
+            Normalization::new().with_limits(Limits::new().with_max_restrictions(2));
+
+        assert!(normalization.normalize(parse(b"a OR b").unwrap()).is_ok());
+        assert!(matches!(
+            normalization.normalize(parse(b"a OR b OR c").unwrap()),
+            Err(Error::Parse(rapiere_parser::Error::TooManyRestrictions {
+                max: 2
+            }))
+        ));
+    }
+}
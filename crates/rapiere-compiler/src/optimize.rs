@@ -0,0 +1,593 @@
+//! Optimization of the bytecode of compiled filters.
+//!
+//! The optimizer lifts a program into the tree of short-circuiting operators its jumps
+//! encode, runs the enabled passes over it, then emits it again, its registers only holding
+//! the values it still refers to:
+//! - [`Pass::FoldConstants`] evaluates comparisons of literals, e.g. `1 = 2` becoming
+//!   `false`, as well as negations of constants;
+//! - [`Pass::EliminateDeadBranches`] removes the operands of `AND` and `OR` which can't
+//!   change their outcome, e.g. `a AND false` becoming `false`;
+//! - [`Pass::RemoveDuplicates`] removes the operands of `AND` and `OR` evaluating the same
+//!   restriction as a previous one;
+//! - [`Pass::ReorderCalls`] moves the operands of `AND` and `OR` calling functions after
+//!   the ones which don't, so that cheap checks short-circuit them;
+//! - [`Pass::ThreadJumps`] retargets jumps landing on a jump of the same kind to the target
+//!   of the latter, e.g. for an `AND` nested in another one.
+//!
+//! Optimized filters match the same records, but skip evaluating the operands which can't
+//! change the outcome, along with the errors evaluating them would raise. Programs which
+//! are not shaped as the compiler emits them, e.g. decoded ones, are left unchanged.
+
+use crate::{
+    filter::Filter,
+    instruction::{Address, Instruction},
+    registers::Literal,
+    vm,
+};
+use rapiere_lexer::Span;
+
+/// Maximum nesting of operators lifted from a program, deeper ones being left unchanged.
+const MAX_DEPTH: usize = 256;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Pass {
+    FoldConstants,
+    EliminateDeadBranches,
+    RemoveDuplicates,
+    ReorderCalls,
+    ThreadJumps,
+}
+
+impl Pass {
+    #[inline(always)]
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Pipeline of optimization passes, every one of them being enabled by default.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Optimizer {
+    passes: u8,
+}
+
+impl Optimizer {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// An optimizer running no pass, only compacting the registers.
+    #[inline(always)]
+    pub fn none() -> Self {
+        Self { passes: 0 }
+    }
+
+    #[inline]
+    pub fn with_pass(mut self, pass: Pass) -> Self {
+        self.passes |= pass.bit();
+        self
+    }
+
+    #[inline]
+    pub fn without_pass(mut self, pass: Pass) -> Self {
+        self.passes &= !pass.bit();
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self, pass: Pass) -> bool {
+        self.passes & pass.bit() != 0
+    }
+
+    pub fn optimize(&self, filter: Filter) -> Filter {
+        self.try_optimize(&filter).unwrap_or(filter)
+    }
+
+    fn try_optimize(&self, filter: &Filter) -> Option<Filter> {
+        let mut node = lift(filter, 0, filter.instructions.len(), 0)?;
+
+        if self.is_enabled(Pass::FoldConstants) {
+            node = fold(node);
+        }
+        if self.is_enabled(Pass::EliminateDeadBranches) {
+            node = eliminate(node);
+        }
+        if self.is_enabled(Pass::RemoveDuplicates) {
+            node = deduplicate(node);
+        }
+        if self.is_enabled(Pass::ReorderCalls) {
+            reorder(&mut node);
+        }
+
+        let mut emitter = Emitter::default();
+        emitter.node(&node)?;
+
+        let mut filter = emitter.filter;
+        if self.is_enabled(Pass::ThreadJumps) {
+            thread(&mut filter.instructions);
+        }
+
+        Some(filter)
+    }
+}
+
+impl Default for Optimizer {
+    #[inline(always)]
+    fn default() -> Self {
+        Self { passes: u8::MAX }
+    }
+}
+
+/// An instruction, along with the values of the registers it refers to.
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    LoadPath(String),
+    LoadLiteral(Literal),
+    Call { function: String, args: u8 },
+    Instruction(Instruction),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operator {
+    And,
+    Or,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    /// Instructions without jumps, each one along with the span it was compiled from
+    Code(Vec<(Op, Span)>),
+
+    Not(Box<Node>),
+
+    Operator(Operator, Vec<Node>),
+}
+
+impl Node {
+    #[inline]
+    fn constant(value: bool, span: Span) -> Self {
+        Self::Code(vec![(Op::LoadLiteral(Literal::Boolean(value)), span)])
+    }
+
+    #[inline]
+    fn as_constant(&self) -> Option<bool> {
+        match self {
+            Self::Code(ops) => match ops.as_slice() {
+                [(Op::LoadLiteral(Literal::Boolean(value)), _)] => Some(*value),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Span of the first instruction of the node.
+    fn span(&self) -> Span {
+        match self {
+            Self::Code(ops) => ops.first().map(|(_, span)| *span).unwrap_or_default(),
+            Self::Not(node) => node.span(),
+            Self::Operator(_, operands) => operands.first().map(Node::span).unwrap_or_default(),
+        }
+    }
+
+    fn calls(&self) -> bool {
+        match self {
+            Self::Code(ops) => ops.iter().any(|(op, _)| matches!(op, Op::Call { .. })),
+            Self::Not(node) => node.calls(),
+            Self::Operator(_, operands) => operands.iter().any(Node::calls),
+        }
+    }
+
+    /// Whether both nodes evaluate the same instructions, whatever their spans.
+    fn same(&self, other: &Node) -> bool {
+        match (self, other) {
+            (Self::Code(lhs), Self::Code(rhs)) => {
+                lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(lhs, rhs)| lhs.0 == rhs.0)
+            }
+            (Self::Not(lhs), Self::Not(rhs)) => lhs.same(rhs),
+            (Self::Operator(lhs, lhs_operands), Self::Operator(rhs, rhs_operands)) => {
+                lhs == rhs
+                    && lhs_operands.len() == rhs_operands.len()
+                    && lhs_operands
+                        .iter()
+                        .zip(rhs_operands)
+                        .all(|(lhs, rhs)| lhs.same(rhs))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Lifts the instructions from `lo` up to `hi` into a node, the jumps to `hi` being the
+/// ones of the outermost operator.
+fn lift(filter: &Filter, lo: usize, hi: usize, depth: usize) -> Option<Node> {
+    if lo >= hi || depth > MAX_DEPTH {
+        return None;
+    }
+
+    let mut operator = None;
+    let mut splits = Vec::new();
+    for (address, instruction) in (lo..hi).zip(&filter.instructions[lo..hi]) {
+        let (kind, target) = match *instruction {
+            Instruction::JumpIfFalse(target) => (Operator::And, target as usize),
+            Instruction::JumpIfTrue(target) => (Operator::Or, target as usize),
+            _ => continue,
+        };
+
+        if target <= address || target > hi {
+            return None;
+        }
+        if target == hi {
+            match operator {
+                None => operator = Some(kind),
+                Some(operator) if operator != kind => {
+                    // Jumps of another operator to `hi` are the ones of its last operand
+                    break;
+                }
+                Some(_) => {}
+            }
+            splits.push(address);
+        }
+    }
+
+    if let Some(operator) = operator {
+        let mut operands = Vec::with_capacity(splits.len() + 1);
+        let mut start = lo;
+
+        for split in splits {
+            operands.push(lift(filter, start, split, depth + 1)?);
+            start = split + 1;
+        }
+        operands.push(lift(filter, start, hi, depth + 1)?);
+
+        return Some(Node::Operator(operator, operands));
+    }
+
+    if hi - lo > 1 && filter.instructions[hi - 1] == Instruction::Not {
+        return Some(Node::Not(Box::new(lift(filter, lo, hi - 1, depth + 1)?)));
+    }
+
+    let mut ops = Vec::with_capacity(hi - lo);
+    for address in lo..hi {
+        let op = match filter.instructions[address] {
+            Instruction::LoadPath(index) => {
+                Op::LoadPath(filter.paths().get(index as usize)?.clone())
+            }
+            Instruction::LoadLiteral(index) => {
+                Op::LoadLiteral(filter.literals().get(index as usize)?.clone())
+            }
+            Instruction::Call { function, args } => Op::Call {
+                function: filter.paths().get(function as usize)?.clone(),
+                args,
+            },
+            // Jumps of composite function arguments
+            Instruction::JumpIfFalse(_) | Instruction::JumpIfTrue(_) => return None,
+            instruction => Op::Instruction(instruction),
+        };
+
+        ops.push((op, filter.spans.get(address).copied().unwrap_or_default()));
+    }
+
+    Some(Node::Code(ops))
+}
+
+/// Value of code comparing or testing literals only.
+fn fold_code(ops: &[(Op, Span)]) -> Option<bool> {
+    match ops {
+        [(lhs, _), (rhs, _), (Op::Instruction(instruction), _)] => {
+            let (Op::LoadLiteral(lhs), Op::LoadLiteral(rhs)) = (lhs, rhs) else {
+                return None;
+            };
+
+            match instruction {
+                Instruction::Has => Some(vm::has_literal(lhs, rhs)),
+                Instruction::Equals
+                | Instruction::NotEquals
+                | Instruction::LesserThan
+                | Instruction::LesserThanEquals
+                | Instruction::GreaterThan
+                | Instruction::GreaterThanEquals => Some(vm::compare(instruction, lhs, rhs)),
+                _ => None,
+            }
+        }
+        [(Op::LoadLiteral(value), _), (Op::Instruction(Instruction::Test), _)] => {
+            Some(value.is_truthy())
+        }
+        _ => None,
+    }
+}
+
+fn fold(node: Node) -> Node {
+    match node {
+        Node::Code(ops) => {
+            let value = fold_code(&ops);
+
+            match value {
+                Some(value) => Node::constant(value, ops[0].1),
+                None => Node::Code(ops),
+            }
+        }
+        Node::Not(node) => {
+            let node = fold(*node);
+
+            match node.as_constant() {
+                Some(value) => Node::constant(!value, node.span()),
+                None => Node::Not(Box::new(node)),
+            }
+        }
+        Node::Operator(operator, operands) => {
+            Node::Operator(operator, operands.into_iter().map(fold).collect())
+        }
+    }
+}
+
+fn eliminate(node: Node) -> Node {
+    match node {
+        Node::Not(node) => Node::Not(Box::new(eliminate(*node))),
+        Node::Operator(operator, operands) => {
+            // `false` for `AND`, `true` for `OR`
+            let absorbing = operator == Operator::Or;
+            let span = operands.first().map(Node::span).unwrap_or_default();
+
+            let mut kept = Vec::with_capacity(operands.len());
+            for operand in operands.into_iter().map(eliminate) {
+                match operand.as_constant() {
+                    Some(value) if value == absorbing => {
+                        return Node::constant(absorbing, operand.span());
+                    }
+                    Some(_) => {}
+                    None => kept.push(operand),
+                }
+            }
+
+            match kept.len() {
+                0 => Node::constant(!absorbing, span),
+                1 => kept.pop().expect("a single operand is kept"),
+                _ => Node::Operator(operator, kept),
+            }
+        }
+        node => node,
+    }
+}
+
+fn deduplicate(node: Node) -> Node {
+    match node {
+        Node::Not(node) => Node::Not(Box::new(deduplicate(*node))),
+        Node::Operator(operator, operands) => {
+            let mut kept: Vec<Node> = Vec::with_capacity(operands.len());
+            for operand in operands.into_iter().map(deduplicate) {
+                if !kept.iter().any(|node| node.same(&operand)) {
+                    kept.push(operand);
+                }
+            }
+
+            match kept.len() {
+                1 => kept.pop().expect("a single operand is kept"),
+                _ => Node::Operator(operator, kept),
+            }
+        }
+        node => node,
+    }
+}
+
+fn reorder(node: &mut Node) {
+    match node {
+        Node::Code(_) => {}
+        Node::Not(node) => reorder(node),
+        Node::Operator(_, operands) => {
+            operands.iter_mut().for_each(reorder);
+            // Stable, keeping the order of the operands calling functions, and of the others
+            operands.sort_by_key(Node::calls);
+        }
+    }
+}
+
+/// Retargets jumps landing on a jump of the same kind, which would jump again with the
+/// same condition.
+fn thread(instructions: &mut [Instruction]) {
+    // Backwards, the jumps a jump lands on being threaded already
+    for address in (0..instructions.len()).rev() {
+        instructions[address] = match instructions[address] {
+            Instruction::JumpIfFalse(target) => match instructions.get(target as usize) {
+                Some(Instruction::JumpIfFalse(next)) => Instruction::JumpIfFalse(*next),
+                _ => continue,
+            },
+            Instruction::JumpIfTrue(target) => match instructions.get(target as usize) {
+                Some(Instruction::JumpIfTrue(next)) => Instruction::JumpIfTrue(*next),
+                _ => continue,
+            },
+            _ => continue,
+        };
+    }
+}
+
+/// Emitter of lifted nodes into a new filter, interning the values they refer to.
+#[derive(Default)]
+struct Emitter {
+    filter: Filter,
+}
+
+impl Emitter {
+    fn node(&mut self, node: &Node) -> Option<()> {
+        match node {
+            Node::Code(ops) => {
+                for (op, span) in ops {
+                    let instruction = match op {
+                        Op::LoadPath(path) => {
+                            Instruction::LoadPath(self.filter.paths.intern(path.clone()).ok()?)
+                        }
+                        Op::LoadLiteral(literal) => Instruction::LoadLiteral(
+                            self.filter.literals.intern(literal.clone()).ok()?,
+                        ),
+                        Op::Call { function, args } => Instruction::Call {
+                            function: self.filter.paths.intern(function.clone()).ok()?,
+                            args: *args,
+                        },
+                        Op::Instruction(instruction) => *instruction,
+                    };
+
+                    self.emit(instruction, *span);
+                }
+            }
+            Node::Not(node) => {
+                self.node(node)?;
+                self.emit(Instruction::Not, Span::default());
+            }
+            Node::Operator(operator, operands) => {
+                let jump = match operator {
+                    Operator::And => Instruction::JumpIfFalse,
+                    Operator::Or => Instruction::JumpIfTrue,
+                };
+                let mut jumps = Vec::with_capacity(operands.len());
+
+                for (idx, operand) in operands.iter().enumerate() {
+                    self.node(operand)?;
+
+                    if idx + 1 < operands.len() {
+                        jumps.push(self.filter.instructions.len());
+                        self.emit(jump(0), Span::default());
+                    }
+                }
+
+                let end = Address::try_from(self.filter.instructions.len()).ok()?;
+                for idx in jumps {
+                    self.filter.instructions[idx] = jump(end);
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    #[inline(always)]
+    fn emit(&mut self, instruction: Instruction, span: Span) {
+        self.filter.instructions.push(instruction);
+        self.filter.spans.push(span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile::{compile, Compiler},
+        record::Value,
+    };
+    use rapiere_parser::parse;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
+    use Instruction::*;
+
+    #[rstest]
+    #[case::fold_constants(
+        b"a = 1 AND 1 = 2",
+        &[Pass::FoldConstants],
+        &[LoadLiteral(0), LoadLiteral(1), Equals, JumpIfFalse(7), LoadPath(0), LoadLiteral(0), Equals],
+        &[LoadLiteral(0), JumpIfFalse(5), LoadPath(0), LoadLiteral(1), Equals]
+    )]
+    #[case::fold_negation(
+        b"a OR NOT (1 = 1)",
+        &[Pass::FoldConstants],
+        &[LoadLiteral(0), LoadLiteral(0), Equals, Not, JumpIfTrue(7), LoadPath(0), Test],
+        &[LoadLiteral(0), JumpIfTrue(4), LoadPath(0), Test]
+    )]
+    #[case::eliminate_absorbing_branch(
+        b"a = 1 AND 1 = 2",
+        &[Pass::FoldConstants, Pass::EliminateDeadBranches],
+        &[LoadLiteral(0), LoadLiteral(1), Equals, JumpIfFalse(7), LoadPath(0), LoadLiteral(0), Equals],
+        &[LoadLiteral(0)]
+    )]
+    #[case::eliminate_neutral_branch(
+        b"a AND 1 = 1",
+        &[Pass::FoldConstants, Pass::EliminateDeadBranches],
+        &[LoadLiteral(0), LoadLiteral(0), Equals, JumpIfFalse(6), LoadPath(0), Test],
+        &[LoadPath(0), Test]
+    )]
+    #[case::remove_duplicates(
+        b"a = (1 OR 1)",
+        &[Pass::RemoveDuplicates],
+        &[LoadPath(0), LoadLiteral(0), Equals, JumpIfTrue(7), LoadPath(0), LoadLiteral(0), Equals],
+        &[LoadPath(0), LoadLiteral(0), Equals]
+    )]
+    #[case::reorder_calls(
+        b"a(x) = 1 AND b",
+        &[Pass::ReorderCalls],
+        &[LoadPath(0), Call { function: 1, args: 1 }, LoadLiteral(0), Equals, JumpIfFalse(7), LoadPath(2), Test],
+        &[LoadPath(0), Test, JumpIfFalse(7), LoadPath(1), Call { function: 2, args: 1 }, LoadLiteral(0), Equals]
+    )]
+    #[case::thread_jumps(
+        b"a = (1 AND 2) AND b",
+        &[Pass::ThreadJumps],
+        &[
+            LoadPath(0), LoadLiteral(0), Equals, JumpIfFalse(7),
+            LoadPath(0), LoadLiteral(1), Equals, JumpIfFalse(10),
+            LoadPath(1), Test,
+        ],
+        &[
+            LoadPath(0), LoadLiteral(0), Equals, JumpIfFalse(10),
+            LoadPath(0), LoadLiteral(1), Equals, JumpIfFalse(10),
+            LoadPath(1), Test,
+        ]
+    )]
+    #[case::unchanged(
+        b"a = 1 AND 1 = 2",
+        &[],
+        &[LoadLiteral(0), LoadLiteral(1), Equals, JumpIfFalse(7), LoadPath(0), LoadLiteral(0), Equals],
+        &[LoadLiteral(0), LoadLiteral(1), Equals, JumpIfFalse(7), LoadPath(0), LoadLiteral(0), Equals]
+    )]
+    fn it_optimizes_a_filter(
+        #[case] input: &[u8],
+        #[case] passes: &[Pass],
+        #[case] before: &[Instruction],
+        #[case] after: &[Instruction],
+    ) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+        assert_eq!(filter.instructions(), before);
+
+        let optimizer = passes.iter().fold(Optimizer::none(), |optimizer, pass| {
+            optimizer.with_pass(*pass)
+        });
+        assert_eq!(optimizer.optimize(filter).instructions(), after);
+    }
+
+    #[test]
+    fn it_compacts_the_registers() {
+        let compiler = Compiler::new().with_optimizer(Optimizer::new());
+        let filter = compiler
+            .compile(parse(b"a AND (b = 1 OR 1 = 1)").unwrap())
+            .unwrap();
+
+        assert_eq!(filter.instructions(), &[LoadPath(0), Test]);
+        assert_eq!(filter.literals(), &[]);
+        assert_eq!(filter.paths(), &["a"]);
+    }
+
+    #[test]
+    fn it_leaves_unrecognized_programs_unchanged() {
+        let filter = compile(parse(b"f((a OR b)) = 1 AND 1 = 1").unwrap()).unwrap();
+
+        let optimized = Optimizer::new().optimize(filter.clone());
+        assert_eq!(optimized.instructions(), filter.instructions());
+    }
+
+    #[rstest]
+    #[case(b"a = 1 AND 1 = 2")]
+    #[case(b"a = 1 OR 1 = 1")]
+    #[case(b"a = (1 OR 1) AND NOT (1 = 2)")]
+    #[case(b"a = (1 AND 2) AND b OR c:\"*\"")]
+    #[case(b"(a = 1 OR b) AND (a = 2 OR NOT b) AND c = (1 OR 2 OR 1)")]
+    fn it_preserves_the_filter_semantics(#[case] input: &[u8]) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+        let optimized = Optimizer::new().optimize(filter.clone());
+
+        for (a, b, c) in [(1, true, 1), (2, false, 2), (1, false, 3), (2, true, 1)] {
+            let record = BTreeMap::from([
+                ("a".to_owned(), Value::Literal(Literal::Integer(a))),
+                ("b".to_owned(), Value::Literal(Literal::Boolean(b))),
+                ("c".to_owned(), Value::Literal(Literal::Integer(c))),
+            ]);
+
+            assert_eq!(optimized.matches(&record), filter.matches(&record));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{error::EvalError, registers::Literal, vm};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A value resolved from a record.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    Literal(Literal),
+
+    /// Repeated field
+    List(Vec<Value>),
+
+    Map(BTreeMap<String, Value>),
+
+    /// Value of a path which the record does not hold
+    #[default]
+    Missing,
+}
+
+impl Value {
+    /// Resolves a dot-joined path from the value, traversing maps by their keys.
+    ///
+    /// Traversing a list resolves the path from each of its elements, e.g. `a.b` resolving
+    /// the `b` of every element of the `a` list, elements without it being left out. Values
+    /// held by the value are borrowed, while the ones resolved from lists are collected.
+    pub fn get(&self, path: &str) -> Cow<'_, Value> {
+        if path.is_empty() {
+            return Cow::Borrowed(self);
+        }
+
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+        match self {
+            Self::Map(entries) => entries
+                .get(key)
+                .map_or(Cow::Owned(Value::Missing), |value| value.get(rest)),
+            Self::List(values) => Cow::Owned(Self::List(
+                values
+                    .iter()
+                    .map(|value| value.get(path))
+                    .filter(|value| **value != Value::Missing)
+                    .map(Cow::into_owned)
+                    .collect(),
+            )),
+            _ => Cow::Owned(Value::Missing),
+        }
+    }
+
+    /// Whether the value would match as a global restriction.
+    #[inline]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Literal(literal) => literal.is_truthy(),
+            Self::List(values) => !values.is_empty(),
+            Self::Map(entries) => !entries.is_empty(),
+            Self::Missing => false,
+        }
+    }
+}
+
+impl From<Literal> for Value {
+    #[inline(always)]
+    fn from(literal: Literal) -> Self {
+        Self::Literal(literal)
+    }
+}
+
+/// Source of the values a compiled filter is evaluated against.
+pub trait Record {
+    /// Resolves a dot-joined path, e.g. `a.b`, borrowing the value when the record holds it
+    /// so that evaluations don't allocate.
+    fn get(&self, path: &str) -> Cow<'_, Value>;
+
+    /// Whether the value of a path has the argument, as `:` does, e.g. `a.b:x`.
+    ///
+    /// The value is resolved with [`Record::get`] by default, records overriding it to
+    /// match the elements of the lists the path traverses without collecting them.
+    #[inline]
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        vm::has(&self.get(path), arg)
+    }
+
+    /// Calls a function, e.g. `math.mem`, no function being supported by default.
+    fn call(&self, function: &str, _args: &[Value]) -> Result<Value, EvalError> {
+        Err(EvalError::UnknownFunction(function.to_owned()))
+    }
+}
+
+/// A value is the root of the record, e.g. a map of its top-level fields.
+impl Record for Value {
+    #[inline(always)]
+    fn get(&self, path: &str) -> Cow<'_, Value> {
+        Value::get(self, path)
+    }
+
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        if path.is_empty() {
+            return vm::has(self, arg);
+        }
+
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+        match self {
+            Self::Map(entries) => entries
+                .get(key)
+                .is_some_and(|value| Record::has(value, rest, arg)),
+            // Any list has `*`, even when none of its elements holds the path
+            Self::List(_) if matches!(arg, Literal::String(pattern) if pattern == "*") => true,
+            Self::List(values) => values.iter().any(|value| Record::has(value, path, arg)),
+            _ => false,
+        }
+    }
+}
+
+impl Record for BTreeMap<String, Value> {
+    #[inline]
+    fn get(&self, path: &str) -> Cow<'_, Value> {
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+
+        BTreeMap::get(self, key).map_or(Cow::Owned(Value::Missing), |value| value.get(rest))
+    }
+
+    #[inline]
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        let (key, rest) = path.split_once('.').unwrap_or((path, ""));
+
+        BTreeMap::get(self, key).is_some_and(|value| Record::has(value, rest, arg))
+    }
+}
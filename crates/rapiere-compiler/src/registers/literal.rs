@@ -1,13 +1,217 @@
-#[derive(Clone, Debug, Default, PartialEq)]
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use rapiere_parser::Value;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// A literal of a filter, held by the literal register.
+///
+/// Literals are equal when they are identical, floats being compared by their bits so that
+/// a `NaN` equals itself and is interned once, while `-0.0` and `0.0` are distinct, and
+/// timestamps when they denote the same instant. The stack machine compares values by
+/// their semantics instead.
+#[derive(Clone, Debug, Default)]
 pub enum Literal {
     Boolean(bool),
 
+    Bytes(Vec<u8>),
+
+    /// Seconds, e.g. `"3.5s"`
+    Duration(TimeDelta),
+
+    /// Bare word, e.g. `ACTIVE` in `state = ACTIVE`
+    EnumValue(String),
+
     Float(f32),
 
     Integer(i64),
 
     String(String),
 
+    /// RFC 3339 timestamp, e.g. `"2024-01-01T00:00:00Z"`
+    Timestamp(DateTime<FixedOffset>),
+
     #[default]
     Null,
 }
+
+impl Literal {
+    /// Whether the literal would match as a global restriction.
+    #[inline]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Boolean(value) => *value,
+            Self::Bytes(value) => !value.is_empty(),
+            Self::Duration(value) => !value.is_zero(),
+            Self::EnumValue(value) | Self::String(value) => !value.is_empty(),
+            Self::Float(value) => *value != 0.0,
+            Self::Integer(value) => *value != 0,
+            Self::Timestamp(_) => true,
+            Self::Null => false,
+        }
+    }
+}
+
+impl From<Value> for Literal {
+    /// Bare words are enum values, while strings holding RFC 3339 timestamps or durations in
+    /// seconds are timestamps and durations.
+    #[inline]
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Text(value) => Self::EnumValue(value),
+            Value::String(value) => {
+                if let Some(timestamp) = parse_timestamp(&value) {
+                    Self::Timestamp(timestamp)
+                } else if let Some(duration) = parse_duration(&value) {
+                    Self::Duration(duration)
+                } else {
+                    Self::String(value)
+                }
+            }
+            Value::Integer(value) => Self::Integer(value),
+            Value::Float(value) => Self::Float(value),
+            Value::Boolean(value) => Self::Boolean(value),
+            Value::Null => Self::Null,
+        }
+    }
+}
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Boolean(lhs), Self::Boolean(rhs)) => lhs == rhs,
+            (Self::Bytes(lhs), Self::Bytes(rhs)) => lhs == rhs,
+            (Self::Duration(lhs), Self::Duration(rhs)) => lhs == rhs,
+            (Self::EnumValue(lhs), Self::EnumValue(rhs)) => lhs == rhs,
+            (Self::Float(lhs), Self::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            (Self::Integer(lhs), Self::Integer(rhs)) => lhs == rhs,
+            (Self::String(lhs), Self::String(rhs)) => lhs == rhs,
+            (Self::Timestamp(lhs), Self::Timestamp(rhs)) => lhs == rhs,
+            (Self::Null, Self::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Boolean(value) => value.hash(state),
+            Self::Bytes(value) => value.hash(state),
+            Self::Duration(value) => value.hash(state),
+            Self::EnumValue(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::Integer(value) => value.hash(state),
+            Self::String(value) => value.hash(state),
+            Self::Timestamp(value) => value.hash(state),
+            Self::Null => {}
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boolean(value) => write!(f, "{value}"),
+            Self::Bytes(value) => write!(f, "b\"{}\"", value.escape_ascii()),
+            Self::Duration(value) => {
+                let sign = if *value < TimeDelta::zero() { "-" } else { "" };
+                let value = value.abs();
+                let nanos = format!("{:09}", value.subsec_nanos());
+
+                match nanos.trim_end_matches('0') {
+                    "" => write!(f, "{sign}{}s", value.num_seconds()),
+                    nanos => write!(f, "{sign}{}.{nanos}s", value.num_seconds()),
+                }
+            }
+            Self::EnumValue(value) => f.write_str(value),
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::String(value) => write!(f, "{value:?}"),
+            Self::Timestamp(value) => f.write_str(&value.to_rfc3339()),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `2024-01-01T00:00:00Z`.
+#[inline]
+pub(crate) fn parse_timestamp(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+/// Parses a duration in seconds with up to nanosecond precision, e.g. `3.5s` or `-20s`.
+pub(crate) fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let value = value.strip_suffix('s')?;
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let (seconds, fraction) = value.split_once('.').unwrap_or((value, ""));
+
+    let digits = |value: &str| value.bytes().all(|byte| byte.is_ascii_digit());
+    if seconds.is_empty() || !digits(seconds) || !digits(fraction) || fraction.len() > 9 {
+        return None;
+    }
+
+    let seconds = seconds.parse::<i64>().ok()?;
+    let nanos = match fraction {
+        "" => 0,
+        fraction => fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32),
+    };
+    let duration = TimeDelta::new(seconds, nanos)?;
+
+    Some(if negative { -duration } else { duration })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::enum_value(Value::Text("ACTIVE".to_owned()), Literal::EnumValue("ACTIVE".to_owned()))]
+    #[case::string(Value::String("ACTIVE".to_owned()), Literal::String("ACTIVE".to_owned()))]
+    #[case::timestamp(
+        Value::String("2024-01-01T01:00:00+01:00".to_owned()),
+        Literal::Timestamp(DateTime::from_timestamp(1704067200, 0).unwrap().fixed_offset())
+    )]
+    #[case::duration(Value::String("3.5s".to_owned()), Literal::Duration(TimeDelta::milliseconds(3500)))]
+    #[case::negative_duration(Value::String("-20s".to_owned()), Literal::Duration(TimeDelta::seconds(-20)))]
+    #[case::not_a_duration(Value::String("3.s.s".to_owned()), Literal::String("3.s.s".to_owned()))]
+    #[case::not_a_timestamp(Value::String("2024-01-01".to_owned()), Literal::String("2024-01-01".to_owned()))]
+    fn it_converts_a_value(#[case] value: Value, #[case] expected: Literal) {
+        assert_eq!(Literal::from(value), expected);
+    }
+
+    #[rstest]
+    #[case::bytes(Literal::Bytes(vec![b'a', 0]), "b\"a\\x00\"")]
+    #[case::duration(Literal::Duration(TimeDelta::milliseconds(-3500)), "-3.5s")]
+    #[case::whole_duration(Literal::Duration(TimeDelta::seconds(20)), "20s")]
+    #[case::enum_value(Literal::EnumValue("ACTIVE".to_owned()), "ACTIVE")]
+    #[case::float(Literal::Float(2.0), "2.0")]
+    #[case::string(Literal::String("a \"b\"".to_owned()), "\"a \\\"b\\\"\"")]
+    #[case::timestamp(
+        Literal::Timestamp(DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap()),
+        "2024-01-01T00:00:00+00:00"
+    )]
+    fn it_displays_a_literal(#[case] literal: Literal, #[case] expected: &str) {
+        assert_eq!(literal.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("0s", Some(TimeDelta::zero()))]
+    #[case("1.000000001s", Some(TimeDelta::new(1, 1).unwrap()))]
+    #[case("0.25s", Some(TimeDelta::milliseconds(250)))]
+    #[case("1.0000000001s", None)]
+    #[case("s", None)]
+    #[case(".5s", None)]
+    #[case("1m", None)]
+    fn it_parses_a_duration(#[case] input: &str, #[case] expected: Option<TimeDelta>) {
+        assert_eq!(parse_duration(input), expected);
+    }
+}
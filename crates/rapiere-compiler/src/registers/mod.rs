@@ -1,9 +1,57 @@
 pub use literal::Literal;
-use register::Register;
+pub(crate) use literal::{parse_duration, parse_timestamp};
+pub use register::Register;
 
 mod literal;
 mod register;
 
-pub type Index = u8;
+pub type Index = u32;
 pub type LiteralRegister = Register<Literal>;
 pub type PathRegister = Register<String>;
+
+/// Narrowest width of the indices into registers of a given size, e.g. for encoding a
+/// filter's instructions compactly.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IndexWidth {
+    U8,
+    U16,
+    U32,
+}
+
+impl IndexWidth {
+    /// Width fitting every index into a register holding `len` values.
+    #[inline]
+    pub fn of(len: usize) -> Self {
+        match len {
+            0..=0x100 => Self::U8,
+            0x101..=0x1_0000 => Self::U16,
+            _ => Self::U32,
+        }
+    }
+
+    /// Number of bytes of an index.
+    #[inline(always)]
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(0, IndexWidth::U8)]
+    #[case::u8(256, IndexWidth::U8)]
+    #[case::u16(257, IndexWidth::U16)]
+    #[case::u16_max(65536, IndexWidth::U16)]
+    #[case::u32(65537, IndexWidth::U32)]
+    fn it_chooses_the_index_width(#[case] len: usize, #[case] expected: IndexWidth) {
+        assert_eq!(IndexWidth::of(len), expected);
+    }
+}
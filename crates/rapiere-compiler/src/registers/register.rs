@@ -1,55 +1,258 @@
 use super::Index;
+use crate::error::RegisterError;
+use hashbrown::HashTable;
 use smallvec::{smallvec, SmallVec};
+use std::{
+    fmt,
+    hash::{BuildHasher, Hash, RandomState},
+};
 
-#[derive(Clone, Debug)]
-pub struct Register<T: PartialEq>(SmallVec<[T; 8]>);
+/// Values a filter's instructions refer to by their index, the first few ones being held
+/// inline.
+///
+/// Values are indexed by their hash, so that finding the index of a value, hence interning
+/// it, takes constant time whatever the size of the register. Values can't be borrowed
+/// mutably, as mutating one in place would leave it indexed by a stale hash: registers
+/// deliberately have no `get_mut`, values being removed and pushed again instead.
+#[derive(Clone)]
+pub struct Register<T: Eq + Hash> {
+    values: SmallVec<[T; 8]>,
 
-impl<T: PartialEq> Register<T> {
-    pub const MAX_SIZE: usize = 256;
+    /// Indices of the values, hashed as the values they refer to
+    indices: HashTable<Index>,
+    hasher: RandomState,
+    max_size: usize,
+}
+
+impl<T: Eq + Hash> Register<T> {
+    pub const MAX_SIZE: usize = Index::MAX as usize;
 
     #[inline(always)]
     pub fn new() -> Self {
         Default::default()
     }
 
+    /// Caps the number of values the register can hold, at most [`Self::MAX_SIZE`].
+    #[inline]
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size.min(Self::MAX_SIZE);
+        self
+    }
+
     #[inline(always)]
     pub fn get(&self, index: Index) -> Option<&T> {
-        self.0.get(index as usize)
+        self.values.get(index as usize)
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[T] {
+        self.values.as_slice()
     }
 
     #[inline(always)]
-    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
-        self.0.get_mut(index as usize)
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
     }
 
     #[inline(always)]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.values.len()
     }
 
-    pub fn push(&mut self, value: T) -> Result<Option<&T>, ()> {
-        if self.remaining_size() == 0 || self.0.contains(&value) {
-            return Err(());
+    /// Index of a value held by the register.
+    #[inline]
+    pub fn position(&self, value: &T) -> Option<Index> {
+        let hash = self.hasher.hash_one(value);
+
+        self.indices
+            .find(hash, |index| self.values[*index as usize] == *value)
+            .copied()
+    }
+
+    /// Pushes a value the register does not hold yet, returning its index.
+    pub fn push(&mut self, value: T) -> Result<Index, RegisterError> {
+        let hash = self.hasher.hash_one(&value);
+        let Self {
+            values,
+            indices,
+            hasher,
+            max_size,
+        } = self;
+
+        if let Some(index) = indices.find(hash, |index| values[*index as usize] == value) {
+            return Err(RegisterError::Duplicate { index: *index });
+        }
+        if values.len() >= *max_size {
+            return Err(RegisterError::Full { max: *max_size });
         }
 
-        self.0.push(value);
-        Ok(self.0.last())
+        let index = values.len() as Index;
+        values.push(value);
+        indices.insert_unique(hash, index, |index| {
+            hasher.hash_one(&values[*index as usize])
+        });
+
+        Ok(index)
+    }
+
+    /// Index of a value, the value being pushed into the register unless it already holds
+    /// it.
+    pub fn intern(&mut self, value: T) -> Result<Index, RegisterError> {
+        match self.push(value) {
+            Err(RegisterError::Duplicate { index }) => Ok(index),
+            result => result,
+        }
     }
 
     #[inline(always)]
     pub fn remaining_size(&self) -> usize {
-        Self::MAX_SIZE - self.0.len()
+        self.max_size.saturating_sub(self.values.len())
     }
 
     #[inline(always)]
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Removes the value at the index, shifting the following ones, which takes time
+    /// proportional to the size of the register as its values are indexed again.
     pub fn remove(&mut self, index: Index) -> T {
-        self.0.remove(index as usize)
+        let value = self.values.remove(index as usize);
+
+        let Self {
+            values,
+            indices,
+            hasher,
+            ..
+        } = self;
+        indices.clear();
+        for (index, held) in values.iter().enumerate() {
+            indices.insert_unique(hasher.hash_one(held), index as Index, |index| {
+                hasher.hash_one(&values[*index as usize])
+            });
+        }
+
+        value
     }
 }
 
-impl<T: PartialEq> Default for Register<T> {
+impl<T: Eq + Hash> Default for Register<T> {
     #[inline(always)]
     fn default() -> Self {
-        Self(smallvec![])
+        Self {
+            values: smallvec![],
+            indices: HashTable::new(),
+            hasher: RandomState::new(),
+            max_size: Self::MAX_SIZE,
+        }
+    }
+}
+
+impl<T: Eq + Hash + fmt::Debug> fmt::Debug for Register<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Register")
+            .field("values", &self.values)
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registers::Literal;
+    use rstest::rstest;
+    use std::{cell::Cell, hash::Hasher};
+
+    #[rstest]
+    #[case::integer(Literal::Integer(42), Literal::Integer(7))]
+    #[case::nan(Literal::Float(f32::NAN), Literal::Float(1.0))]
+    #[case::signed_zero(Literal::Float(-0.0), Literal::Float(0.0))]
+    fn it_interns_a_value(#[case] value: Literal, #[case] other: Literal) {
+        let mut register = Register::new();
+
+        assert_eq!(register.intern(value.clone()), Ok(0));
+        assert_eq!(register.intern(other.clone()), Ok(1));
+        assert_eq!(register.intern(value.clone()), Ok(0));
+        assert_eq!(register.intern(other), Ok(1));
+        assert_eq!(register.len(), 2);
+        assert_eq!(
+            register.push(value),
+            Err(RegisterError::Duplicate { index: 0 })
+        );
+    }
+
+    #[test]
+    fn it_fails_when_full() {
+        let mut register = Register::new().with_max_size(300);
+        for value in 0..300 {
+            assert_eq!(register.intern(value.to_string()), Ok(value as Index));
+        }
+
+        assert_eq!(register.intern("0".to_owned()), Ok(0));
+        assert_eq!(
+            register.intern("a".to_owned()),
+            Err(RegisterError::Full { max: 300 })
+        );
+        assert_eq!(
+            register.push("0".to_owned()),
+            Err(RegisterError::Duplicate { index: 0 })
+        );
+    }
+
+    #[test]
+    fn it_reindexes_removed_values() {
+        let mut register = Register::new();
+        for value in ["a", "b", "c"] {
+            register.intern(value.to_owned()).unwrap();
+        }
+
+        assert_eq!(register.remove(0), "a");
+        assert_eq!(register.position(&"a".to_owned()), None);
+        assert_eq!(register.position(&"c".to_owned()), Some(1));
+        assert_eq!(register.intern("a".to_owned()), Ok(2));
+    }
+
+    #[test]
+    fn it_interns_in_constant_time() {
+        thread_local! {
+            static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        /// Value counting the comparisons of the thread.
+        #[derive(Debug, Eq)]
+        struct Counted(usize);
+
+        impl Hash for Counted {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.hash(state);
+            }
+        }
+
+        impl PartialEq for Counted {
+            fn eq(&self, other: &Self) -> bool {
+                COMPARISONS.with(|comparisons| comparisons.set(comparisons.get() + 1));
+                self.0 == other.0
+            }
+        }
+
+        const LEN: usize = 10_000;
+
+        let mut register = Register::new();
+        for value in 0..LEN {
+            assert_eq!(register.intern(Counted(value)), Ok(value as Index));
+        }
+        for value in (0..LEN).rev() {
+            assert_eq!(register.intern(Counted(value)), Ok(value as Index));
+        }
+        assert_eq!(register.len(), LEN);
+
+        // Scanning the register for duplicates would compare each value with the previous
+        // ones, while hashing only compares the values whose hashes collide
+        let comparisons = COMPARISONS.with(Cell::get);
+        assert!(
+            comparisons <= 2 * LEN,
+            "interning compared {comparisons} values"
+        );
     }
 }
@@ -0,0 +1,661 @@
+//! Stack machine evaluating compiled filters against records.
+//!
+//! Comparisons follow AIP-160:
+//! - integers and floats compare as numbers, strings lexicographically, and values of
+//!   different types are neither equal nor ordered;
+//! - timestamps and durations compare chronologically, strings holding them being parsed,
+//!   e.g. a record's `"2024-01-01T00:00:00Z"`, while bytes compare lexicographically with
+//!   bytes and strings;
+//! - enum values equal enum values and strings of the same name, and are not ordered;
+//! - a missing value compares as `null`;
+//! - `=` and `:` match strings against patterns holding `*` wildcards, e.g. `"*.txt"`;
+//! - `:` matches a list holding a matching element, a map holding the key, or any value
+//!   present when its argument is `*`.
+//!
+//! Lists and maps can only be compared with `:`.
+
+use crate::{
+    error::EvalError,
+    filter::Filter,
+    instruction::{Address, Instruction},
+    record::{Record, Value},
+    registers::{parse_duration, parse_timestamp, Literal},
+};
+use std::{borrow::Cow, cmp::Ordering};
+
+/// Mutable state of evaluations, reused from one to the next so that evaluating filters
+/// only allocates their stack once the buffers have grown.
+///
+/// Slots of the stack borrowing from the filter and the record, the stack can't outlive an
+/// evaluation: the context keeps the largest capacity it needed instead, so that the next
+/// stacks are allocated at once rather than growing.
+///
+/// Evaluations also allocate whatever the record allocates, such as:
+/// - the values of paths traversing lists, which [`Value`] records collect unless `:` is
+///   applied to them, lists being walked by [`Record::has`] instead;
+/// - the arguments of functions, cloned into the buffer of the context, which allocates
+///   for strings, bytes, lists and maps, along with whatever the functions allocate.
+///
+/// Functions being called by name through the record, the context caches none of them,
+/// records keeping whatever state they need.
+///
+/// Compiled filters being immutable, threads can share one while evaluating it with a
+/// context of their own.
+#[derive(Clone, Debug, Default)]
+pub struct EvalContext {
+    /// Capacity the stacks of the machine are allocated with
+    capacity: usize,
+
+    /// Arguments of the function being called
+    args: Vec<Value>,
+}
+
+impl EvalContext {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// A context whose stack holds `capacity` values before growing.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            args: Vec::new(),
+        }
+    }
+}
+
+impl Filter {
+    /// Whether the record matches the filter, evaluated with the buffers of the context.
+    pub fn evaluate(
+        &self,
+        context: &mut EvalContext,
+        record: &impl Record,
+    ) -> Result<bool, EvalError> {
+        let mut stack = Vec::with_capacity(context.capacity);
+        let matches = self.run(&mut stack, &mut context.args, record);
+        context.capacity = context.capacity.max(stack.capacity());
+
+        matches
+    }
+
+    /// Whether the record matches the filter, evaluated with a new context.
+    #[inline]
+    pub fn matches(&self, record: &impl Record) -> Result<bool, EvalError> {
+        self.evaluate(&mut EvalContext::new(), record)
+    }
+
+    fn run<'a, R: Record>(
+        &'a self,
+        stack: &mut Vec<Slot<'a>>,
+        args: &mut Vec<Value>,
+        record: &'a R,
+    ) -> Result<bool, EvalError> {
+        let instructions = self.instructions();
+        let mut address = 0;
+
+        while let Some(instruction) = instructions.get(address) {
+            let at = address as Address;
+            let malformed = || EvalError::Malformed { address: at };
+            address += 1;
+
+            match *instruction {
+                Instruction::LoadPath(index) => {
+                    let path = self.paths().get(index as usize).ok_or_else(malformed)?;
+                    stack.push(Slot::Path(path));
+                }
+                Instruction::LoadLiteral(index) => {
+                    let literal = self.literals().get(index as usize).ok_or_else(malformed)?;
+                    stack.push(Slot::Literal(literal));
+                }
+                Instruction::Equals
+                | Instruction::NotEquals
+                | Instruction::LesserThan
+                | Instruction::LesserThanEquals
+                | Instruction::GreaterThan
+                | Instruction::GreaterThanEquals => {
+                    let (lhs, rhs) = pop_operands(stack, record).ok_or_else(malformed)?;
+                    let (Some(lhs), Some(rhs)) = (lhs.literal(), rhs.literal()) else {
+                        return Err(EvalError::NotComparable { address: at });
+                    };
+
+                    stack.push(Slot::Condition(compare(instruction, lhs, rhs)));
+                }
+                Instruction::Has => {
+                    let rhs = stack.pop().ok_or_else(malformed)?.resolve(record);
+                    let lhs = stack.pop().ok_or_else(malformed)?;
+                    let Some(rhs) = rhs.literal() else {
+                        return Err(EvalError::NotComparable { address: at });
+                    };
+
+                    let matches = match &lhs {
+                        Slot::Path(path) => record.has(path, rhs),
+                        Slot::Value(value) => has(value, rhs),
+                        slot => slot
+                            .literal()
+                            .is_some_and(|literal| has_literal(literal, rhs)),
+                    };
+                    stack.push(Slot::Condition(matches));
+                }
+                Instruction::Call {
+                    function,
+                    args: len,
+                } => {
+                    let name = self.paths().get(function as usize).ok_or_else(malformed)?;
+                    let start = stack
+                        .len()
+                        .checked_sub(len as usize)
+                        .ok_or_else(malformed)?;
+
+                    args.clear();
+                    args.extend(
+                        stack
+                            .drain(start..)
+                            .map(|slot| slot.resolve(record).into_value()),
+                    );
+                    stack.push(Slot::Value(Cow::Owned(record.call(name, args)?)));
+                }
+                Instruction::Test => {
+                    let slot = stack.pop().ok_or_else(malformed)?.resolve(record);
+                    stack.push(Slot::Condition(slot.is_truthy()));
+                }
+                Instruction::Not => {
+                    let condition = pop_condition(stack).ok_or_else(malformed)?;
+                    stack.push(Slot::Condition(!condition));
+                }
+                Instruction::JumpIfFalse(target) | Instruction::JumpIfTrue(target) => {
+                    let condition = pop_condition(stack).ok_or_else(malformed)?;
+                    let jumps = matches!(instruction, Instruction::JumpIfTrue(_)) == condition;
+
+                    if jumps {
+                        // The condition is kept as the result of the short-circuited operator
+                        stack.push(Slot::Condition(condition));
+
+                        // Jumping forward only, programs always terminate
+                        if target <= at || target as usize > instructions.len() {
+                            return Err(malformed());
+                        }
+                        address = target as usize;
+                    }
+                }
+            }
+        }
+
+        match (pop_condition(stack), stack.is_empty()) {
+            (Some(matches), true) => Ok(matches),
+            _ => Err(EvalError::Malformed {
+                address: instructions.len() as Address,
+            }),
+        }
+    }
+}
+
+/// A value of the stack, borrowed from the filter or the record whenever possible.
+#[derive(Clone, Debug)]
+enum Slot<'a> {
+    Condition(bool),
+    Literal(&'a Literal),
+
+    /// Path whose value is resolved once the instruction popping it is known, so that `:`
+    /// can be applied to it without resolving it
+    Path(&'a str),
+
+    Value(Cow<'a, Value>),
+}
+
+impl<'a> Slot<'a> {
+    /// Slot holding the value of a path, other slots being kept as they are.
+    #[inline]
+    fn resolve<R: Record>(self, record: &'a R) -> Self {
+        match self {
+            Self::Path(path) => Self::Value(record.get(path)),
+            slot => slot,
+        }
+    }
+
+    /// Literal of a scalar slot, missing values being `null`, and paths not being resolved.
+    #[inline]
+    fn literal(&self) -> Option<&Literal> {
+        match self {
+            Self::Condition(true) => Some(&Literal::Boolean(true)),
+            Self::Condition(false) => Some(&Literal::Boolean(false)),
+            Self::Literal(literal) => Some(literal),
+            Self::Path(_) => None,
+            Self::Value(value) => literal(value),
+        }
+    }
+
+    /// Whether a resolved slot is truthy.
+    #[inline]
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Condition(condition) => *condition,
+            Self::Literal(literal) => literal.is_truthy(),
+            Self::Path(_) => false,
+            Self::Value(value) => value.is_truthy(),
+        }
+    }
+
+    /// Value of a resolved slot.
+    #[inline]
+    fn into_value(self) -> Value {
+        match self {
+            Self::Condition(condition) => Value::Literal(Literal::Boolean(condition)),
+            Self::Literal(literal) => Value::Literal(literal.clone()),
+            Self::Path(_) => Value::Missing,
+            Self::Value(value) => value.into_owned(),
+        }
+    }
+}
+
+#[inline]
+fn pop_condition(stack: &mut Vec<Slot>) -> Option<bool> {
+    match stack.pop()?.literal()? {
+        Literal::Boolean(condition) => Some(*condition),
+        _ => None,
+    }
+}
+
+/// Operands of a comparison, their paths being resolved.
+#[inline]
+fn pop_operands<'a, R: Record>(
+    stack: &mut Vec<Slot<'a>>,
+    record: &'a R,
+) -> Option<(Slot<'a>, Slot<'a>)> {
+    let rhs = stack.pop()?.resolve(record);
+    let lhs = stack.pop()?.resolve(record);
+
+    Some((lhs, rhs))
+}
+
+/// Literal of a scalar value, missing values being `null`.
+#[inline]
+fn literal(value: &Value) -> Option<&Literal> {
+    match value {
+        Value::Literal(literal) => Some(literal),
+        Value::Missing => Some(&Literal::Null),
+        Value::List(_) | Value::Map(_) => None,
+    }
+}
+
+/// Result of a comparison instruction, other than `:`.
+pub(crate) fn compare(instruction: &Instruction, lhs: &Literal, rhs: &Literal) -> bool {
+    match instruction {
+        Instruction::Equals => equals(lhs, rhs),
+        Instruction::NotEquals => !equals(lhs, rhs),
+        Instruction::LesserThan => order(lhs, rhs) == Some(Ordering::Less),
+        Instruction::LesserThanEquals => {
+            matches!(order(lhs, rhs), Some(Ordering::Less | Ordering::Equal))
+        }
+        Instruction::GreaterThan => order(lhs, rhs) == Some(Ordering::Greater),
+        _ => matches!(order(lhs, rhs), Some(Ordering::Greater | Ordering::Equal)),
+    }
+}
+
+#[inline]
+fn equals(lhs: &Literal, rhs: &Literal) -> bool {
+    match (lhs, rhs) {
+        (Literal::String(value) | Literal::EnumValue(value), Literal::String(pattern))
+            if pattern.contains('*') =>
+        {
+            wildcard(pattern.as_bytes(), value.as_bytes())
+        }
+        (
+            Literal::String(lhs) | Literal::EnumValue(lhs),
+            Literal::String(rhs) | Literal::EnumValue(rhs),
+        ) => lhs == rhs,
+        _ => order(lhs, rhs) == Some(Ordering::Equal),
+    }
+}
+
+fn order(lhs: &Literal, rhs: &Literal) -> Option<Ordering> {
+    match (lhs, rhs) {
+        (Literal::Integer(lhs), Literal::Integer(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::Integer(lhs), Literal::Float(rhs)) => (*lhs as f64).partial_cmp(&(*rhs as f64)),
+        (Literal::Float(lhs), Literal::Integer(rhs)) => (*lhs as f64).partial_cmp(&(*rhs as f64)),
+        (Literal::Float(lhs), Literal::Float(rhs)) => lhs.partial_cmp(rhs),
+        (Literal::String(lhs), Literal::String(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::Boolean(lhs), Literal::Boolean(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::Timestamp(lhs), Literal::Timestamp(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::String(lhs), Literal::Timestamp(rhs)) => Some(parse_timestamp(lhs)?.cmp(rhs)),
+        (Literal::Timestamp(lhs), Literal::String(rhs)) => Some(lhs.cmp(&parse_timestamp(rhs)?)),
+        (Literal::Duration(lhs), Literal::Duration(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::String(lhs), Literal::Duration(rhs)) => Some(parse_duration(lhs)?.cmp(rhs)),
+        (Literal::Duration(lhs), Literal::String(rhs)) => Some(lhs.cmp(&parse_duration(rhs)?)),
+        (Literal::Bytes(lhs), Literal::Bytes(rhs)) => Some(lhs.cmp(rhs)),
+        (Literal::Bytes(lhs), Literal::String(rhs)) => Some(lhs.as_slice().cmp(rhs.as_bytes())),
+        (Literal::String(lhs), Literal::Bytes(rhs)) => Some(lhs.as_bytes().cmp(rhs.as_slice())),
+        (Literal::Null, Literal::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+pub(crate) fn has(value: &Value, arg: &Literal) -> bool {
+    match (value, arg) {
+        (Value::Missing, _) => false,
+        (Value::Literal(literal), arg) => has_literal(literal, arg),
+        (_, Literal::String(pattern)) if pattern == "*" => true,
+        (Value::List(values), arg) => values.iter().any(|value| has(value, arg)),
+        (Value::Map(entries), Literal::String(key) | Literal::EnumValue(key)) => {
+            entries.contains_key(key)
+        }
+        (Value::Map(_), _) => false,
+    }
+}
+
+/// Result of `:` for a scalar value.
+pub(crate) fn has_literal(literal: &Literal, arg: &Literal) -> bool {
+    match (literal, arg) {
+        (Literal::Null, _) => false,
+        (_, Literal::String(pattern)) if pattern == "*" => true,
+        (literal, arg) => equals(literal, arg),
+    }
+}
+
+/// Whether a text matches a pattern in which `*` matches any sequence of bytes.
+fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern, and of the text it currently matches up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(byte) if *byte == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|byte| *byte == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::compile;
+    use chrono::DateTime;
+    use rapiere_parser::parse;
+    use rstest::rstest;
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        collections::BTreeMap,
+    };
+
+    /// Allocator counting the allocations of each thread.
+    struct Counting;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+
+    fn map<const N: usize>(entries: [(&str, Value); N]) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    fn string(value: &str) -> Value {
+        Value::Literal(Literal::String(value.to_owned()))
+    }
+
+    fn record() -> Value {
+        map([
+            ("name", string("report.txt")),
+            ("size", Value::Literal(Literal::Integer(42))),
+            ("ratio", Value::Literal(Literal::Float(0.5))),
+            ("public", Value::Literal(Literal::Boolean(true))),
+            ("owner", Value::Literal(Literal::Null)),
+            ("state", string("ACTIVE")),
+            ("create_time", string("2024-03-01T12:00:00Z")),
+            (
+                "update_time",
+                Value::Literal(Literal::Timestamp(
+                    DateTime::from_timestamp(1711800000, 0)
+                        .unwrap()
+                        .fixed_offset(),
+                )),
+            ),
+            ("ttl", string("3600s")),
+            ("checksum", Value::Literal(Literal::Bytes(b"ab".to_vec()))),
+            ("tags", Value::List(vec![string("draft"), string("final")])),
+            ("labels", map([("env", string("prod"))])),
+            (
+                "reviews",
+                Value::List(vec![
+                    map([("author", string("alice"))]),
+                    map([("author", string("bob"))]),
+                ]),
+            ),
+        ])
+    }
+
+    #[rstest]
+    #[case::empty(b"", true)]
+    #[case::equals(b"size = 42", true)]
+    #[case::not_equals(b"size != 42", false)]
+    #[case::numbers(b"size > 41.5 AND ratio < 1 AND ratio >= 0.5", true)]
+    #[case::strings(b"name >= \"report\" AND name < \"s\"", true)]
+    #[case::mismatched_types(b"size = \"42\" OR size < \"a\"", false)]
+    #[case::wildcard(b"name = \"*.txt\" AND name = \"rep*t*\" AND name != \"*.pdf\"", true)]
+    #[case::missing(b"missing = null AND owner = null AND missing != 1", true)]
+    #[case::missing_ordering(b"missing < 1 OR missing >= 1", false)]
+    #[case::global(b"public AND NOT owner AND NOT missing", true)]
+    #[case::has_element(b"tags:final AND NOT tags:review", true)]
+    #[case::has_wildcard(b"tags:\"dra*\"", true)]
+    #[case::has_key(b"labels:env AND NOT labels:region", true)]
+    #[case::has_value(b"labels.env:prod", true)]
+    #[case::has_present(b"name:\"*\" AND NOT owner:\"*\" AND NOT missing:\"*\"", true)]
+    #[case::repeated_traversal(b"reviews.author:bob AND NOT reviews.author:carol", true)]
+    #[case::composite(b"size = (1 OR 42) AND name:(\"*.pdf\" OR \"*.txt\")", true)]
+    #[case::enum_value(b"state = ACTIVE AND state != DELETED AND NOT state > ACTIVE", true)]
+    #[case::timestamps(
+        b"create_time > \"2024-01-01T00:00:00Z\" AND create_time < \"2024-03-01T14:00:00+01:00\"",
+        true
+    )]
+    #[case::timestamp_instant(b"update_time = \"2024-03-30T13:00:00+01:00\"", true)]
+    #[case::not_a_timestamp(
+        b"name > \"2024-01-01T00:00:00Z\" OR name < \"2024-01-01T00:00:00Z\"",
+        false
+    )]
+    #[case::durations(b"ttl >= \"60s\" AND ttl < \"3600.5s\" AND ttl = \"3600s\"", true)]
+    #[case::bytes(b"checksum > \"a\" AND checksum < \"b\" AND checksum = \"ab\"", true)]
+    #[case::short_circuit_and(b"size = 1 AND tags = 1", false)]
+    #[case::short_circuit_or(b"size = 42 OR tags = 1", true)]
+    fn it_matches_a_record(#[case] input: &[u8], #[case] expected: bool) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+        let matches = filter.matches(&record());
+
+        assert!(matches.is_ok(), "{}", matches.unwrap_err());
+        assert_eq!(matches.unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::list(b"tags = \"final\"")]
+    #[case::map(b"labels > 1")]
+    #[case::unknown_function(b"f(size) = 1")]
+    fn it_fails_to_evaluate(#[case] input: &[u8]) {
+        let filter = compile(parse(input).unwrap()).unwrap();
+
+        assert!(filter.matches(&record()).is_err());
+    }
+
+    #[test]
+    fn it_calls_record_functions() {
+        struct Files(BTreeMap<String, Value>);
+
+        impl Record for Files {
+            fn get(&self, path: &str) -> Cow<'_, Value> {
+                Record::get(&self.0, path)
+            }
+
+            fn call(&self, function: &str, args: &[Value]) -> Result<Value, EvalError> {
+                match (function, args) {
+                    ("ext", [Value::Literal(Literal::String(name))]) => Ok(name
+                        .rsplit_once('.')
+                        .map_or(Value::Missing, |(_, ext)| string(ext))),
+                    _ => Err(EvalError::UnknownFunction(function.to_owned())),
+                }
+            }
+        }
+
+        let Value::Map(entries) = record() else {
+            unreachable!()
+        };
+        let filter = compile(parse(b"ext(name) = \"txt\"").unwrap()).unwrap();
+
+        assert!(filter.matches(&Files(entries)).unwrap());
+    }
+
+    #[rstest]
+    #[case::underflow(vec![Instruction::Equals])]
+    #[case::missing_literal(vec![Instruction::LoadLiteral(3)])]
+    #[case::backward_jump(vec![
+        Instruction::LoadLiteral(0),
+        Instruction::JumpIfTrue(0),
+    ])]
+    #[case::leftover(vec![Instruction::LoadLiteral(0), Instruction::LoadLiteral(0)])]
+    fn it_rejects_a_malformed_program(#[case] instructions: Vec<Instruction>) {
+        let mut filter = compile(parse(b"true").unwrap()).unwrap();
+        filter.instructions = instructions;
+
+        assert!(matches!(
+            filter.matches(&record()),
+            Err(EvalError::Malformed { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case(b"", b"", true)]
+    #[case(b"*", b"anything", true)]
+    #[case(b"a*c", b"abbbc", true)]
+    #[case(b"a*c", b"abbbd", false)]
+    #[case(b"*a*b", b"xaxxb", true)]
+    #[case(b"**", b"", true)]
+    #[case(b"a", b"ab", false)]
+    fn it_matches_wildcards(#[case] pattern: &[u8], #[case] text: &[u8], #[case] expected: bool) {
+        assert_eq!(wildcard(pattern, text), expected);
+    }
+
+    #[rstest]
+    #[case::key("labels", "env")]
+    #[case::member("labels.env", "prod")]
+    #[case::element("tags", "final")]
+    #[case::traversal("reviews.author", "bob")]
+    #[case::traversal_wildcard("reviews.author", "*")]
+    #[case::traversal_mismatch("reviews.author", "carol")]
+    #[case::traversal_missing("reviews.missing", "bob")]
+    #[case::traversal_scalar("name.missing", "*")]
+    #[case::missing("missing.author", "*")]
+    fn it_walks_lists_lazily(#[case] path: &str, #[case] arg: &str) {
+        let record = record();
+        let arg = Literal::String(arg.to_owned());
+
+        assert_eq!(
+            Record::has(&record, path, &arg),
+            has(&record.get(path), &arg)
+        );
+        let Value::Map(entries) = record else {
+            unreachable!()
+        };
+        assert_eq!(
+            Record::has(&entries, path, &arg),
+            has(&Record::get(&entries, path), &arg)
+        );
+    }
+
+    #[test]
+    fn it_evaluates_with_a_single_allocation() {
+        /// Record supporting a function which doesn't allocate.
+        struct Sizes(Value);
+
+        impl Record for Sizes {
+            fn get(&self, path: &str) -> Cow<'_, Value> {
+                self.0.get(path)
+            }
+
+            fn has(&self, path: &str, arg: &Literal) -> bool {
+                Record::has(&self.0, path, arg)
+            }
+
+            fn call(&self, function: &str, args: &[Value]) -> Result<Value, EvalError> {
+                match (function, args) {
+                    ("double", [Value::Literal(Literal::Integer(value))]) => {
+                        Ok(Value::Literal(Literal::Integer(value * 2)))
+                    }
+                    _ => Err(EvalError::UnknownFunction(function.to_owned())),
+                }
+            }
+        }
+
+        let input = b"name = \"*.txt\" AND size > 1 AND tags:final AND labels.env = prod AND \
+            NOT owner:\"*\" AND create_time < \"2025-01-01T00:00:00Z\" AND (ratio < 0 OR public) \
+            AND reviews.author:bob AND NOT reviews.author:carol AND reviews.missing:\"*\" AND \
+            double(size) = 84 AND double(double(1)) > 3";
+        let filter = compile(parse(input).unwrap()).unwrap();
+        let record = Sizes(record());
+
+        let mut context = EvalContext::new();
+        assert_eq!(filter.evaluate(&mut context, &record), Ok(true));
+
+        // Only the stack is allocated, at its full capacity
+        let before = ALLOCATIONS.with(Cell::get);
+        for _ in 0..100 {
+            assert_eq!(filter.evaluate(&mut context, &record), Ok(true));
+        }
+        assert_eq!(ALLOCATIONS.with(Cell::get) - before, 100);
+    }
+
+    #[test]
+    fn it_shares_a_filter_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Filter>();
+        assert_send_sync::<EvalContext>();
+
+        let filter = compile(parse(b"size > 40 AND tags:draft").unwrap()).unwrap();
+        let record = record();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut context = EvalContext::with_capacity(8);
+
+                    for _ in 0..100 {
+                        assert_eq!(filter.evaluate(&mut context, &record), Ok(true));
+                    }
+                });
+            }
+        });
+    }
+}
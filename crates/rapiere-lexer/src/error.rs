@@ -28,6 +28,16 @@ impl Error {
         }
     }
 
+    /// Short description of the error, without its position.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::BadNumber(_) => "bad number format",
+            Self::MalformatedHexNumber(_) => "malformatted hexadecimal integer",
+            Self::UnrecognizedToken(_) => "unrecognized token",
+            Self::UnterminatedStringLiteral(_) => "non-terminated string literal",
+        }
+    }
+
     pub fn set_position(&mut self, line: u64, column: u64) {
         match *self {
             Self::BadNumber(ref mut pos) => *pos = Some((line, column)),
@@ -42,12 +52,7 @@ impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let msg = match self {
-            Self::BadNumber(_) => "bad number format",
-            Self::MalformatedHexNumber(_) => "malformatted hexadecimal integer",
-            Self::UnrecognizedToken(_) => "unrecognized token",
-            Self::UnterminatedStringLiteral(_) => "non-terminated string literal",
-        };
+        let msg = self.message();
         let (line, column) = self.position();
 
         write!(
@@ -32,6 +32,11 @@ impl<'i> Lexer<'i> {
         self.scanner.line()
     }
 
+    #[inline(always)]
+    pub fn offset(&self) -> usize {
+        self.scanner.offset()
+    }
+
     pub fn next_token(&mut self) -> Result<Option<Token>, Error> {
         if self.eof {
             return Ok(None);
@@ -46,6 +51,7 @@ impl<'i> Lexer<'i> {
                 TokenKind::EOF,
                 self.scanner.line(),
                 self.scanner.column(),
+                self.scanner.offset(),
                 0,
             )
         };
@@ -53,6 +59,16 @@ impl<'i> Lexer<'i> {
         Ok(Some(token))
     }
 
+    /// Skips the invalid token the last [`Lexer::next_token`] call failed on, returning
+    /// the number of skipped bytes.
+    ///
+    /// This lets a caller report every invalid token of an input, rather than stopping at
+    /// the first one.
+    #[inline(always)]
+    pub fn skip_invalid_token(&mut self) -> usize {
+        self.scanner.skip_invalid_token(self.input)
+    }
+
     #[inline(always)]
     pub fn reset(&mut self, input: &'i [u8]) {
         self.eof = false;
@@ -166,6 +182,71 @@ mod tests {
         assert_eq!(token.value, expected_value);
     }
 
+    #[rstest]
+    #[case::unterminated_string(b"foo = \"bar", (1, 7))]
+    #[case::malformated_hex_number(b"foo\n  = 0x", (2, 5))]
+    #[case::bad_number(b"(a)\n\nb = 1__2", (3, 5))]
+    #[case::unrecognized_token(b"a AND\n !", (2, 2))]
+    fn it_reports_the_error_position(#[case] input: &[u8], #[case] expected: (u64, u64)) {
+        let mut lexer = Lexer::new(input);
+
+        let err = loop {
+            match lexer.next_token() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("input should not be scanned successfully"),
+                Err(err) => break err,
+            }
+        };
+
+        assert_eq!(err.position(), (Some(expected.0), Some(expected.1)));
+    }
+
+    #[rstest]
+    #[case::first_token(b"foo = 1", 0, (1, 1, 0, 3))]
+    #[case::after_whitespace(b"foo = 1", 2, (1, 5, 4, 1))]
+    #[case::after_newline(b"foo\n  != 1", 4, (2, 3, 6, 2))]
+    #[case::eof(b"a", 1, (1, 2, 1, 0))]
+    fn it_records_the_token_start(
+        #[case] input: &[u8],
+        #[case] index: usize,
+        #[case] expected: (u64, u64, usize, usize),
+    ) {
+        let mut lexer = Lexer::new(input);
+
+        let token = (0..=index)
+            .map(|_| lexer.next_token().unwrap().unwrap())
+            .last()
+            .unwrap();
+
+        assert_eq!(
+            (token.line, token.column, token.offset, token.length),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case::unrecognized_token(b"a ! b", 1, TokenKind::Identifier)]
+    #[case::bad_number(b"1__2)", 4, TokenKind::RightParenthesis)]
+    #[case::unterminated_string(b"\"foo bar", 8, TokenKind::EOF)]
+    fn it_skips_an_invalid_token(
+        #[case] input: &[u8],
+        #[case] expected_length: usize,
+        #[case] expected_kind: TokenKind,
+    ) {
+        let mut lexer = Lexer::new(input);
+
+        while lexer.next_token().is_ok() {}
+        assert_eq!(lexer.skip_invalid_token(), expected_length);
+
+        let token = loop {
+            let token = lexer.next_token().unwrap().unwrap();
+            if token.kind != TokenKind::Whitespace {
+                break token;
+            }
+        };
+        assert_eq!(token.kind, expected_kind);
+    }
+
     #[test]
     fn it_read_an_input_of_tokens() {
         let input = b" ():,.-=!=>>=<<=42\"hello world\"3.1415truefalsenullANDORNOTfoo_bar\n";
@@ -1,10 +1,12 @@
 mod error;
 mod lexer;
 mod scanner;
+mod span;
 mod token;
 mod tokenizer;
 
 pub use error::Error;
 pub use lexer::Lexer;
 pub use scanner::Scanner;
+pub use span::Span;
 pub use token::{Token, TokenKind, TokenValue};
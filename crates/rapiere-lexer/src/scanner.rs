@@ -32,7 +32,7 @@ impl Scanner {
 
     #[inline]
     fn consume(&mut self, input: &[u8], amount: usize) {
-        for b in &input[..amount] {
+        for b in &input[self.offset..self.offset + amount] {
             if *b == b'\n' {
                 self.line += 1;
                 self.column = 1;
@@ -49,6 +49,11 @@ impl Scanner {
         self.line
     }
 
+    #[inline(always)]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     #[inline(always)]
     pub fn mark_current_position(&mut self) {
         self.mark = (self.offset, self.line, self.column);
@@ -77,18 +82,16 @@ impl Scanner {
             let window = &input[self.offset..];
             match self.tokenizer.tokenize(window) {
                 Ok((Some((kind, word)), length)) => {
+                    let token = Token::new(kind, self.line, self.column, self.offset, length);
                     self.consume(input, length);
 
                     let token = match kind {
                         TokenKind::Undefined => {
                             unreachable!("undefined token should result into an error");
                         }
-                        TokenKind::Identifier => Token::new(kind, self.line, self.column, length)
-                            .with_value(String::from_utf8_lossy(word)),
-                        TokenKind::Literal => {
-                            Token::new(kind, self.line, self.column, length).with_value(word)
-                        }
-                        _ => Token::new(kind, self.line, self.column, length),
+                        TokenKind::Identifier => token.with_value(String::from_utf8_lossy(word)),
+                        TokenKind::Literal => token.with_value(word),
+                        _ => token,
                     };
 
                     return Ok(Some(token));
@@ -110,6 +113,28 @@ impl Scanner {
             return Ok(None);
         }
     }
+
+    /// Skips the invalid token the last [`Scanner::scan`] call failed on, so that scanning
+    /// can resume after it.
+    ///
+    /// A string literal missing its closing quote runs until the end of the input, any
+    /// other invalid token runs until the next whitespace or parenthesis. Returns the
+    /// number of skipped bytes.
+    pub fn skip_invalid_token(&mut self, input: &[u8]) -> usize {
+        let window = &input[self.offset.min(input.len())..];
+        let length = match window.first() {
+            None => 0,
+            Some(b'"') => window.len(),
+            Some(_) => window
+                .iter()
+                .skip(1)
+                .position(|b| b.is_ascii_whitespace() || *b == b'(' || *b == b')')
+                .map_or(window.len(), |end| end + 1),
+        };
+
+        self.consume(input, length);
+        length
+    }
 }
 
 impl fmt::Debug for Scanner {
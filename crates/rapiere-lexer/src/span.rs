@@ -0,0 +1,39 @@
+/// Location of a fragment of the input, `line` and `column` being the ones of its first
+/// byte.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub offset: usize,
+    pub length: usize,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Span {
+    #[inline(always)]
+    pub fn new(offset: usize, length: usize, line: u64, column: u64) -> Self {
+        Self {
+            offset,
+            length,
+            line,
+            column,
+        }
+    }
+
+    /// Offset of the first byte following the span.
+    #[inline(always)]
+    pub fn end(&self) -> usize {
+        self.offset + self.length
+    }
+
+    /// Joins two spans into one covering both of them, as well as anything in between.
+    #[inline]
+    pub fn to(&self, other: Span) -> Span {
+        let (start, end) = if self.offset <= other.offset {
+            (self, other.end().max(self.end()))
+        } else {
+            (&other, self.end().max(other.end()))
+        };
+
+        Span::new(start.offset, end - start.offset, start.line, start.column)
+    }
+}
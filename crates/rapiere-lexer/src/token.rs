@@ -1,27 +1,36 @@
+use crate::span::Span;
 use chrono::{DateTime, FixedOffset, TimeDelta, TimeZone};
 use std::{borrow::Cow, fmt};
 
+/// A token scanned from the input, `line` and `column` being the ones of its first byte.
 #[derive(Clone, Debug, Default)]
 pub struct Token {
     pub kind: TokenKind,
     pub line: u64,
     pub column: u64,
+    pub offset: usize,
     pub length: usize,
     pub value: Option<TokenValue>,
 }
 
 impl Token {
     #[inline(always)]
-    pub fn new(kind: TokenKind, line: u64, column: u64, length: usize) -> Self {
+    pub fn new(kind: TokenKind, line: u64, column: u64, offset: usize, length: usize) -> Self {
         Self {
             kind,
             line,
             column,
+            offset,
             length,
             value: None,
         }
     }
 
+    #[inline(always)]
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.length, self.line, self.column)
+    }
+
     #[inline]
     pub fn with_value(mut self, value: impl Into<TokenValue>) -> Self {
         self.value = Some(value.into());
@@ -0,0 +1,141 @@
+//! Expansion of a parsed filter into the code building its tree.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use rapiere_parser::{Arg, Comparable, Comparator, Expression, Filter, Restriction, Span, Value};
+
+pub(crate) fn filter(filter: &Filter) -> TokenStream {
+    let expression = match &filter.expression {
+        Some(expression) => {
+            let expression = self::expression(expression);
+            quote!(::core::option::Option::Some(#expression))
+        }
+        None => quote!(::core::option::Option::None),
+    };
+
+    quote!(::rapiere::parser::Filter::new(#expression))
+}
+
+fn expression(expression: &Expression) -> TokenStream {
+    match expression {
+        Expression::And(operands) => {
+            let operands = operands.iter().map(self::expression);
+            quote!(::rapiere::parser::Expression::And(
+                ::std::vec![#(#operands),*]
+            ))
+        }
+        Expression::Sequence(operands) => {
+            let operands = operands.iter().map(self::expression);
+            quote!(::rapiere::parser::Expression::Sequence(
+                ::std::vec![#(#operands),*]
+            ))
+        }
+        Expression::Or(operands) => {
+            let operands = operands.iter().map(self::expression);
+            quote!(::rapiere::parser::Expression::Or(
+                ::std::vec![#(#operands),*]
+            ))
+        }
+        Expression::Not(operand) => {
+            let operand = self::expression(operand);
+            quote!(::rapiere::parser::Expression::Not(::std::boxed::Box::new(#operand)))
+        }
+        Expression::Restriction(restriction) => {
+            let restriction = self::restriction(restriction);
+            quote!(::rapiere::parser::Expression::Restriction(#restriction))
+        }
+        Expression::Error(_) => unreachable!("filters holding errors are not expanded"),
+    }
+}
+
+fn restriction(restriction: &Restriction) -> TokenStream {
+    let span = span(restriction.span);
+    let comparable = comparable(&restriction.comparable);
+    let comparison = match &restriction.comparison {
+        Some((comparator, arg)) => {
+            let comparator = self::comparator(*comparator);
+            let arg = self::arg(arg);
+            quote!(::core::option::Option::Some((#comparator, #arg)))
+        }
+        None => quote!(::core::option::Option::None),
+    };
+
+    quote!(::rapiere::parser::Restriction::new(#span, #comparable, #comparison))
+}
+
+fn span(span: Span) -> TokenStream {
+    let Span {
+        offset,
+        length,
+        line,
+        column,
+    } = span;
+
+    quote!(::rapiere::parser::Span::new(#offset, #length, #line, #column))
+}
+
+fn comparator(comparator: Comparator) -> TokenStream {
+    let variant = match comparator {
+        Comparator::Equals => quote!(Equals),
+        Comparator::NotEquals => quote!(NotEquals),
+        Comparator::LesserThan => quote!(LesserThan),
+        Comparator::LesserThanEquals => quote!(LesserThanEquals),
+        Comparator::GreaterThan => quote!(GreaterThan),
+        Comparator::GreaterThanEquals => quote!(GreaterThanEquals),
+        Comparator::Has => quote!(Has),
+    };
+
+    quote!(::rapiere::parser::Comparator::#variant)
+}
+
+fn comparable(comparable: &Comparable) -> TokenStream {
+    match comparable {
+        Comparable::Member(member) => {
+            let value = value(&member.value);
+            let fields = &member.fields;
+
+            quote!(::rapiere::parser::Comparable::Member(::rapiere::parser::Member::new(
+                #value,
+                ::std::vec![#(::std::string::String::from(#fields)),*],
+            )))
+        }
+        Comparable::Function(function) => {
+            let name = &function.name;
+            let args = function.args.iter().map(arg);
+
+            quote!(::rapiere::parser::Comparable::Function(::rapiere::parser::Function::new(
+                #name,
+                ::std::vec![#(#args),*],
+            )))
+        }
+    }
+}
+
+fn arg(arg: &Arg) -> TokenStream {
+    match arg {
+        Arg::Comparable(comparable) => {
+            let comparable = self::comparable(comparable);
+            quote!(::rapiere::parser::Arg::Comparable(#comparable))
+        }
+        Arg::Composite(expression) => {
+            let expression = self::expression(expression);
+            quote!(::rapiere::parser::Arg::Composite(::std::boxed::Box::new(#expression)))
+        }
+        Arg::Error(_) => unreachable!("filters holding errors are not expanded"),
+    }
+}
+
+fn value(value: &Value) -> TokenStream {
+    match value {
+        Value::Text(value) => {
+            quote!(::rapiere::parser::Value::Text(::std::string::String::from(#value)))
+        }
+        Value::String(value) => {
+            quote!(::rapiere::parser::Value::String(::std::string::String::from(#value)))
+        }
+        Value::Integer(value) => quote!(::rapiere::parser::Value::Integer(#value)),
+        Value::Float(value) => quote!(::rapiere::parser::Value::Float(#value)),
+        Value::Boolean(value) => quote!(::rapiere::parser::Value::Boolean(#value)),
+        Value::Null => quote!(::rapiere::parser::Value::Null),
+    }
+}
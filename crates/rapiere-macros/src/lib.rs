@@ -0,0 +1,46 @@
+//! Procedural macros of rapiere, re-exported by the `rapiere` crate which their expansion
+//! refers to.
+
+mod expand;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use rapiere_parser::{Error, Parser};
+use syn::{parse_macro_input, LitStr};
+
+/// Parses an AIP-160 filter at compile time, expanding to its `rapiere::parser::Filter`.
+///
+/// Every syntax error of the filter is reported as a compile error on the literal.
+#[proc_macro]
+pub fn filter(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let source = literal.value();
+
+    let errors = match Parser::new(source.as_bytes()).parse() {
+        Ok((filter, diagnostics)) if diagnostics.is_empty() => {
+            return expand::filter(&filter).into();
+        }
+        Ok((_, diagnostics)) => diagnostics
+            .iter()
+            .map(|diagnostic| Error::Syntax(vec![diagnostic.clone()]).to_string())
+            .collect(),
+        Err(err) => vec![err.to_string()],
+    };
+
+    let errors = errors
+        .into_iter()
+        .map(|message| syn::Error::new(literal.span(), message))
+        .reduce(|mut errors, err| {
+            errors.combine(err);
+            errors
+        })
+        .expect("a failed parse reports at least one error")
+        .into_compile_error();
+
+    // Still expanding to a filter keeps the errors from cascading into type errors
+    quote! {{
+        #errors
+        ::rapiere::parser::Filter::default()
+    }}
+    .into()
+}
@@ -0,0 +1,158 @@
+use rapiere_lexer::Span;
+
+/// Root of a parsed filter, an empty filter matching everything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub expression: Option<Expression>,
+}
+
+impl Filter {
+    #[inline(always)]
+    pub fn new(expression: Option<Expression>) -> Self {
+        Self { expression }
+    }
+}
+
+/// A node of the filter's expression tree.
+///
+/// Operators bind from the loosest to the tightest in the order `AND`, sequence, `OR` and
+/// `NOT`, as AIP-160 defines them. Parentheses only group operands, hence have no node of
+/// their own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    /// Operands joined by the `AND` keyword
+    And(Vec<Expression>),
+
+    /// Operands separated by whitespaces, which have to match altogether
+    Sequence(Vec<Expression>),
+
+    /// Operands joined by the `OR` keyword
+    Or(Vec<Expression>),
+
+    /// Operand negated by either the `NOT` keyword or `-`
+    Not(Box<Expression>),
+
+    Restriction(Restriction),
+
+    /// Placeholder of an expression which could not be parsed
+    Error(Span),
+}
+
+/// A comparable, optionally compared to an argument, e.g. `a.b >= 42`.
+///
+/// A restriction without comparison is a global restriction, such as `prod`.
+#[derive(Clone, Debug)]
+pub struct Restriction {
+    pub span: Span,
+    pub comparable: Comparable,
+    pub comparison: Option<(Comparator, Arg)>,
+}
+
+impl Restriction {
+    #[inline(always)]
+    pub fn new(span: Span, comparable: Comparable, comparison: Option<(Comparator, Arg)>) -> Self {
+        Self {
+            span,
+            comparable,
+            comparison,
+        }
+    }
+}
+
+impl PartialEq for Restriction {
+    /// Restrictions are compared regardless of where they were parsed, as tokens are.
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.comparable == other.comparable && self.comparison == other.comparison
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Comparator {
+    /// =
+    Equals,
+
+    /// !=
+    NotEquals,
+
+    /// <
+    LesserThan,
+
+    /// <=
+    LesserThanEquals,
+
+    /// >
+    GreaterThan,
+
+    /// >=
+    GreaterThanEquals,
+
+    /// :
+    Has,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Comparable {
+    Member(Member),
+    Function(Function),
+}
+
+/// A value, optionally followed by the fields traversed from it, e.g. `a.b.c`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub value: Value,
+    pub fields: Vec<String>,
+}
+
+impl Member {
+    #[inline(always)]
+    pub fn new(value: Value, fields: Vec<String>) -> Self {
+        Self { value, fields }
+    }
+}
+
+/// A function call, e.g. `math.mem(a, b)`, its name being qualified by dots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+impl Function {
+    #[inline(always)]
+    pub fn new(name: impl Into<String>, args: Vec<Arg>) -> Self {
+        Self {
+            name: name.into(),
+            args,
+        }
+    }
+}
+
+/// Right-hand side of a comparison, or argument of a function call.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    Comparable(Comparable),
+
+    /// A parenthesized expression, e.g. `(1 OR 2)`
+    Composite(Box<Expression>),
+
+    /// Placeholder of an argument which could not be parsed
+    Error(Span),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Bare word, such as a field name or an enum value
+    Text(String),
+
+    /// Quoted string, with its escaped quotes resolved
+    String(String),
+
+    Integer(i64),
+
+    Float(f32),
+
+    Boolean(bool),
+
+    Null,
+}
@@ -0,0 +1,377 @@
+//! Programmatic construction of filters.
+//!
+//! The builder produces the very tree the parser would for the canonical text of the
+//! filter, so that built filters can be mixed with parsed ones, e.g. to add a constraint to
+//! a filter coming from a request:
+//!
+//! ```
+//! use rapiere_parser::{parse, Filter};
+//!
+//! let filter = Filter::field("a.b").eq(3).and(Filter::field("c").has("x*"));
+//!
+//! assert_eq!(filter.to_string(), "a.b = 3 AND c:\"x*\"");
+//! assert_eq!(Filter::from(filter), parse(b"a.b = 3 AND c:\"x*\"").unwrap());
+//! ```
+
+use crate::{
+    ast::{Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value},
+    error::Error,
+    format::is_bare_word,
+};
+use rapiere_lexer::Span;
+use std::ops;
+
+/// Left-hand side of a restriction being built, either a member or a function call.
+#[derive(Clone, Debug)]
+pub struct Field(Comparable);
+
+impl Filter {
+    /// Starts a restriction on a member, its path being split on dots, e.g. `a.b`.
+    ///
+    /// A root which is not a single identifier, e.g. `tenant OR x`, is a string rather than
+    /// text, so that it is rendered quoted instead of injecting operators. Build a
+    /// [`Member`] to traverse fields holding dots.
+    pub fn field(path: &str) -> Field {
+        let mut fields = path.split('.').map(str::to_owned);
+        let root = fields.next().unwrap_or_default();
+        let value = if is_bare_word(&root) {
+            Value::Text(root)
+        } else {
+            Value::String(root)
+        };
+
+        Field::from(Member::new(value, fields.collect()))
+    }
+
+    /// Starts a restriction on the result of a function call, e.g. `math.mem(a, 42)`.
+    ///
+    /// The name must start with an identifier, its other dot-separated segments being
+    /// quoted when rendered if needed.
+    pub fn function<A: Into<Arg>>(
+        name: &str,
+        args: impl IntoIterator<Item = A>,
+    ) -> Result<Field, Error> {
+        let root = name.split('.').next().unwrap_or_default();
+        if !is_bare_word(root) {
+            return Err(Error::InvalidFunctionName {
+                name: name.to_owned(),
+            });
+        }
+
+        Ok(Field::from(Function::new(
+            name,
+            args.into_iter().map(Into::into).collect(),
+        )))
+    }
+}
+
+impl From<Expression> for Filter {
+    #[inline(always)]
+    fn from(expression: Expression) -> Self {
+        Self::new(Some(expression))
+    }
+}
+
+impl Field {
+    /// Builds the `=` restriction
+    #[inline]
+    pub fn eq(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::Equals, arg)
+    }
+
+    /// Builds the `!=` restriction
+    #[inline]
+    pub fn ne(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::NotEquals, arg)
+    }
+
+    /// Builds the `<` restriction
+    #[inline]
+    pub fn lt(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::LesserThan, arg)
+    }
+
+    /// Builds the `<=` restriction
+    #[inline]
+    pub fn le(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::LesserThanEquals, arg)
+    }
+
+    /// Builds the `>` restriction
+    #[inline]
+    pub fn gt(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::GreaterThan, arg)
+    }
+
+    /// Builds the `>=` restriction
+    #[inline]
+    pub fn ge(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::GreaterThanEquals, arg)
+    }
+
+    /// Builds the `:` restriction
+    #[inline]
+    pub fn has(self, arg: impl Into<Arg>) -> Expression {
+        self.compare(Comparator::Has, arg)
+    }
+
+    #[inline]
+    pub fn compare(self, comparator: Comparator, arg: impl Into<Arg>) -> Expression {
+        Expression::Restriction(Restriction::new(
+            Span::default(),
+            self.0,
+            Some((comparator, arg.into())),
+        ))
+    }
+
+    /// Joins the global restriction on the field with another expression by `AND`.
+    #[inline]
+    pub fn and(self, other: impl Into<Expression>) -> Expression {
+        Expression::from(self).and(other)
+    }
+
+    /// Joins the global restriction on the field with another expression by `OR`.
+    #[inline]
+    pub fn or(self, other: impl Into<Expression>) -> Expression {
+        Expression::from(self).or(other)
+    }
+}
+
+impl From<Member> for Field {
+    #[inline(always)]
+    fn from(member: Member) -> Self {
+        Self(Comparable::Member(member))
+    }
+}
+
+impl From<Function> for Field {
+    #[inline(always)]
+    fn from(function: Function) -> Self {
+        Self(Comparable::Function(function))
+    }
+}
+
+/// A field alone is a global restriction, e.g. `a.b`.
+impl From<Field> for Expression {
+    #[inline(always)]
+    fn from(field: Field) -> Self {
+        Self::Restriction(Restriction::new(Span::default(), field.0, None))
+    }
+}
+
+impl Expression {
+    /// Joins the expression with another one by `AND`, chained conjunctions being
+    /// flattened as the parser does.
+    pub fn and(self, other: impl Into<Expression>) -> Self {
+        match self {
+            Self::And(mut operands) => {
+                operands.push(other.into());
+                Self::And(operands)
+            }
+            expression => Self::And(vec![expression, other.into()]),
+        }
+    }
+
+    /// Joins the expression with another one by `OR`, chained disjunctions being flattened
+    /// as the parser does.
+    pub fn or(self, other: impl Into<Expression>) -> Self {
+        match self {
+            Self::Or(mut operands) => {
+                operands.push(other.into());
+                Self::Or(operands)
+            }
+            expression => Self::Or(vec![expression, other.into()]),
+        }
+    }
+}
+
+impl ops::Not for Expression {
+    type Output = Self;
+
+    #[inline(always)]
+    fn not(self) -> Self {
+        Self::Not(Box::new(self))
+    }
+}
+
+impl ops::Not for Field {
+    type Output = Expression;
+
+    #[inline(always)]
+    fn not(self) -> Expression {
+        !Expression::from(self)
+    }
+}
+
+impl From<Field> for Arg {
+    #[inline(always)]
+    fn from(field: Field) -> Self {
+        Self::Comparable(field.0)
+    }
+}
+
+/// An expression argument is parenthesized, e.g. `(1 OR 2)`.
+impl From<Expression> for Arg {
+    #[inline(always)]
+    fn from(expression: Expression) -> Self {
+        Self::Composite(Box::new(expression))
+    }
+}
+
+impl From<Value> for Arg {
+    #[inline(always)]
+    fn from(value: Value) -> Self {
+        Self::Comparable(Comparable::Member(Member::new(value, Vec::new())))
+    }
+}
+
+macro_rules! impl_value_from {
+    ($($ty:ty => |$value:ident| $into:expr),+ $(,)?) => {
+        $(
+            impl From<$ty> for Value {
+                #[inline(always)]
+                fn from($value: $ty) -> Self {
+                    $into
+                }
+            }
+
+            impl From<$ty> for Arg {
+                #[inline(always)]
+                fn from(value: $ty) -> Self {
+                    Value::from(value).into()
+                }
+            }
+        )+
+    };
+}
+
+impl_value_from! {
+    i32 => |value| Value::Integer(value.into()),
+    i64 => |value| Value::Integer(value),
+    bool => |value| Value::Boolean(value),
+    &str => |value| Value::String(value.to_owned()),
+    String => |value| Value::String(value),
+}
+
+macro_rules! impl_value_try_from_float {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            /// Floats which aren't finite once narrowed to an `f32` are rejected, having no
+            /// literal.
+            impl TryFrom<$ty> for Value {
+                type Error = Error;
+
+                #[inline]
+                fn try_from(value: $ty) -> Result<Self, Error> {
+                    let float = value as f32;
+                    if float.is_finite() {
+                        Ok(Value::Float(float))
+                    } else {
+                        Err(Error::NonFiniteFloat(value.into()))
+                    }
+                }
+            }
+
+            impl TryFrom<$ty> for Arg {
+                type Error = Error;
+
+                #[inline(always)]
+                fn try_from(value: $ty) -> Result<Self, Error> {
+                    Value::try_from(value).map(Into::into)
+                }
+            }
+        )+
+    };
+}
+
+impl_value_try_from_float!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::global(Filter::field("prod").into(), "prod")]
+    #[case::traversal(
+        Filter::field("a.b.c").ne(Value::try_from(1.5).unwrap()),
+        "a.b.c != 1.5"
+    )]
+    #[case::escaped_string(Filter::field("a").eq("foo \"bar\""), "a = \"foo \"\"bar\"\"\"")]
+    #[case::member_argument(Filter::field("a").ge(Filter::field("b.c")), "a >= b.c")]
+    #[case::quoted_field(
+        Field::from(Member::new(Value::Text("a".to_owned()), vec!["b.c".to_owned()])).lt(0),
+        "a.\"b.c\" < 0"
+    )]
+    #[case::null(Filter::field("a").eq(Value::Null), "a = null")]
+    #[case::chained_conjunction(
+        Filter::field("a").eq(1).and(Filter::field("b").gt(2)).and(Filter::field("c")),
+        "a = 1 AND b > 2 AND c"
+    )]
+    #[case::nested_conjunction(
+        Filter::field("a").eq(1).and(Filter::field("b").eq(2).and(Filter::field("c"))),
+        "a = 1 AND (b = 2 AND c)"
+    )]
+    #[case::precedence(
+        Filter::field("a").eq(true).and(Filter::field("b").or(Filter::field("c"))).or(Filter::field("d")),
+        "(a = true AND b OR c) OR d"
+    )]
+    #[case::negation((!Filter::field("a").le(-2)).or(!Filter::field("b")), "NOT a <= -2 OR NOT b")]
+    #[case::negated_disjunction(!Filter::field("a").or(Filter::field("b")), "NOT (a OR b)")]
+    #[case::function(
+        Filter::function("math.mem", [Filter::field("a"), Filter::field("b")]).unwrap().gt(1),
+        "math.mem(a, b) > 1"
+    )]
+    #[case::composite(Filter::field("a").has(Filter::field("b").eq(1).or(Filter::field("c"))), "a:(b = 1 OR c)")]
+    fn it_builds_the_parsed_tree(#[case] expression: Expression, #[case] expected: &str) {
+        assert_eq!(expression.to_string(), expected);
+        assert_eq!(
+            Filter::from(expression),
+            parse(expected.as_bytes()).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case::operators(Filter::field("tenant OR x").eq(1), "\"tenant OR x\" = 1")]
+    #[case::parentheses(Filter::field("a) OR (b").into(), "\"a) OR (b\"")]
+    #[case::quotes(Filter::field("a\" OR \"b").eq(1), "\"a\"\" OR \"\"b\" = 1")]
+    #[case::keyword(Filter::field("NOT").has("x"), "\"NOT\":\"x\"")]
+    #[case::literal(Filter::field("1.b").eq(1), "\"1\".b = 1")]
+    #[case::empty(Filter::field("").eq(1), "\"\" = 1")]
+    #[case::field(Filter::field("a.b OR c").lt(0), "a.\"b OR c\" < 0")]
+    #[case::function_segment(
+        Filter::function("math.x) OR (y", [1]).unwrap().eq(1),
+        "math.\"x) OR (y\"(1) = 1"
+    )]
+    fn it_escapes_hostile_names(#[case] expression: Expression, #[case] expected: &str) {
+        let filter = Filter::from(expression);
+
+        assert_eq!(filter.to_string(), expected);
+        assert_eq!(parse(filter.to_string().as_bytes()).unwrap(), filter);
+    }
+
+    #[rstest]
+    #[case::operators("f) OR (g")]
+    #[case::keyword("NOT")]
+    #[case::literal("1.f")]
+    #[case::empty("")]
+    fn it_rejects_an_invalid_function_name(#[case] name: &str) {
+        assert!(matches!(
+            Filter::function(name, [1]),
+            Err(Error::InvalidFunctionName { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case::nan(f64::NAN)]
+    #[case::infinity(f64::INFINITY)]
+    #[case::out_of_range(1e300)]
+    fn it_rejects_a_non_finite_float(#[case] value: f64) {
+        assert!(matches!(
+            Value::try_from(value),
+            Err(Error::NonFiniteFloat(_))
+        ));
+        assert!(Arg::try_from(value).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use rapiere_lexer::{Span, TokenKind};
+use std::fmt;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid filter: {}", Diagnostics(.0))]
+    Syntax(Vec<Diagnostic>),
+
+    #[error("filter of {bytes} bytes exceeds the maximum of {max} bytes")]
+    InputTooLarge { bytes: usize, max: usize },
+
+    #[error("filter exceeds the maximum of {max} tokens")]
+    TooManyTokens { max: usize },
+
+    #[error("filter is too deep, parentheses nesting more than {max} levels")]
+    TooDeep { max: usize },
+
+    #[error("filter exceeds the maximum of {max} restrictions")]
+    TooManyRestrictions { max: usize },
+
+    #[error("function `{name}` exceeds the maximum of {max} arguments")]
+    TooManyFunctionArgs { name: String, max: usize },
+
+    /// A built function whose name does not start with an identifier, e.g. `f) OR (g`
+    #[error("invalid function name `{name}`")]
+    InvalidFunctionName { name: String },
+
+    /// A built float which is infinite, not a number, or out of the range of an `f32`
+    #[error("float {0} has no literal")]
+    NonFiniteFloat(f64),
+}
+
+/// A syntax error located in the parsed input.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[error("{kind} (line: {}, column: {})", span.line, span.column)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    #[inline(always)]
+    pub fn new(kind: DiagnosticKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum DiagnosticKind {
+    /// The lexer could not scan a token
+    #[error("{0}")]
+    InvalidToken(&'static str),
+
+    /// A number literal does not fit into its type
+    #[error("number out of range")]
+    NumberOutOfRange,
+
+    #[error("expected {expected}, found {found}")]
+    UnexpectedToken {
+        expected: &'static str,
+        found: TokenKind,
+    },
+
+    #[error("unclosed parenthesis")]
+    UnclosedParenthesis,
+
+    #[error("unmatched closing parenthesis")]
+    UnmatchedParenthesis,
+}
+
+struct Diagnostics<'d>(&'d [Diagnostic]);
+
+impl fmt::Display for Diagnostics<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, diagnostic) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
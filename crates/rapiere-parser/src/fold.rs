@@ -0,0 +1,196 @@
+//! Owned rewriting of a filter's tree.
+//!
+//! Unlike [`crate::visit_mut`], folding consumes the tree and builds a new one, operators
+//! and restrictions being folded into any kind of expression. This lets passes replace a
+//! restriction by a whole sub-expression, or drop an operator entirely.
+
+use crate::ast::{
+    Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value,
+};
+use rapiere_lexer::Span;
+
+pub trait Fold {
+    fn fold_filter(&mut self, filter: Filter) -> Filter {
+        fold_filter(self, filter)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_and(&mut self, operands: Vec<Expression>) -> Expression {
+        Expression::And(fold_operands(self, operands))
+    }
+
+    fn fold_sequence(&mut self, operands: Vec<Expression>) -> Expression {
+        Expression::Sequence(fold_operands(self, operands))
+    }
+
+    fn fold_or(&mut self, operands: Vec<Expression>) -> Expression {
+        Expression::Or(fold_operands(self, operands))
+    }
+
+    fn fold_not(&mut self, operand: Expression) -> Expression {
+        Expression::Not(Box::new(self.fold_expression(operand)))
+    }
+
+    fn fold_restriction(&mut self, restriction: Restriction) -> Expression {
+        Expression::Restriction(fold_restriction(self, restriction))
+    }
+
+    fn fold_comparable(&mut self, comparable: Comparable) -> Comparable {
+        fold_comparable(self, comparable)
+    }
+
+    fn fold_member(&mut self, member: Member) -> Member {
+        fold_member(self, member)
+    }
+
+    fn fold_field(&mut self, field: String) -> String {
+        field
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        fold_function(self, function)
+    }
+
+    fn fold_comparator(&mut self, comparator: Comparator) -> Comparator {
+        comparator
+    }
+
+    fn fold_arg(&mut self, arg: Arg) -> Arg {
+        fold_arg(self, arg)
+    }
+
+    /// Folds a parenthesized expression used as an argument.
+    fn fold_composite(&mut self, expression: Expression) -> Expression {
+        self.fold_expression(expression)
+    }
+
+    fn fold_value(&mut self, value: Value) -> Value {
+        value
+    }
+
+    /// Folds the placeholder of either an expression or an argument which could not be
+    /// parsed.
+    fn fold_error(&mut self, span: Span) -> Span {
+        span
+    }
+}
+
+pub fn fold_filter<F: Fold + ?Sized>(folder: &mut F, filter: Filter) -> Filter {
+    Filter::new(
+        filter
+            .expression
+            .map(|expression| folder.fold_expression(expression)),
+    )
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::And(operands) => folder.fold_and(operands),
+        Expression::Sequence(operands) => folder.fold_sequence(operands),
+        Expression::Or(operands) => folder.fold_or(operands),
+        Expression::Not(operand) => folder.fold_not(*operand),
+        Expression::Restriction(restriction) => folder.fold_restriction(restriction),
+        Expression::Error(span) => Expression::Error(folder.fold_error(span)),
+    }
+}
+
+pub fn fold_operands<F: Fold + ?Sized>(
+    folder: &mut F,
+    operands: Vec<Expression>,
+) -> Vec<Expression> {
+    operands
+        .into_iter()
+        .map(|operand| folder.fold_expression(operand))
+        .collect()
+}
+
+pub fn fold_restriction<F: Fold + ?Sized>(folder: &mut F, restriction: Restriction) -> Restriction {
+    let comparable = folder.fold_comparable(restriction.comparable);
+    let comparison = restriction
+        .comparison
+        .map(|(comparator, arg)| (folder.fold_comparator(comparator), folder.fold_arg(arg)));
+
+    Restriction::new(restriction.span, comparable, comparison)
+}
+
+pub fn fold_comparable<F: Fold + ?Sized>(folder: &mut F, comparable: Comparable) -> Comparable {
+    match comparable {
+        Comparable::Member(member) => Comparable::Member(folder.fold_member(member)),
+        Comparable::Function(function) => Comparable::Function(folder.fold_function(function)),
+    }
+}
+
+pub fn fold_member<F: Fold + ?Sized>(folder: &mut F, member: Member) -> Member {
+    let value = folder.fold_value(member.value);
+    let fields = member
+        .fields
+        .into_iter()
+        .map(|field| folder.fold_field(field))
+        .collect();
+
+    Member::new(value, fields)
+}
+
+pub fn fold_function<F: Fold + ?Sized>(folder: &mut F, function: Function) -> Function {
+    let args = function
+        .args
+        .into_iter()
+        .map(|arg| folder.fold_arg(arg))
+        .collect();
+
+    Function::new(function.name, args)
+}
+
+pub fn fold_arg<F: Fold + ?Sized>(folder: &mut F, arg: Arg) -> Arg {
+    match arg {
+        Arg::Comparable(comparable) => Arg::Comparable(folder.fold_comparable(comparable)),
+        Arg::Composite(expression) => Arg::Composite(Box::new(folder.fold_composite(*expression))),
+        Arg::Error(span) => Arg::Error(folder.fold_error(span)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    /// Restricts every comparison on `id` to the given tenant.
+    struct Tenant(i64);
+
+    impl Fold for Tenant {
+        fn fold_restriction(&mut self, restriction: Restriction) -> Expression {
+            let restriction = fold_restriction(self, restriction);
+
+            match &restriction.comparable {
+                Comparable::Member(member) if member.to_string() == "id" => {
+                    let tenant = parse(format!("tenant = {}", self.0).as_bytes())
+                        .unwrap()
+                        .expression
+                        .unwrap();
+
+                    Expression::And(vec![Expression::Restriction(restriction), tenant])
+                }
+                _ => Expression::Restriction(restriction),
+            }
+        }
+
+        fn fold_sequence(&mut self, operands: Vec<Expression>) -> Expression {
+            Expression::And(fold_operands(self, operands))
+        }
+    }
+
+    #[rstest]
+    #[case::untouched(b"a = 1", "a = 1")]
+    #[case::restriction(b"id = 1", "id = 1 AND tenant = 42")]
+    #[case::operand(b"a OR NOT id > 2", "a OR NOT (id > 2 AND tenant = 42)")]
+    #[case::sequence(b"a b", "a AND b")]
+    fn it_rewrites_the_tree(#[case] input: &[u8], #[case] expected: &str) {
+        let filter = Tenant(42).fold_filter(parse(input).unwrap());
+
+        assert_eq!(filter.to_string(), expected);
+    }
+}
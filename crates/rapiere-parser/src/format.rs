@@ -0,0 +1,282 @@
+//! Canonical AIP-160 rendering of filters.
+//!
+//! Filters are rendered with a single space around keywords and comparators, except for
+//! `:`, and with as few parentheses as needed for the output to parse back into the same
+//! tree. `-` negations are rendered as `NOT`, numbers in decimal without separators and
+//! strings with their quotes doubled.
+
+use crate::{
+    ast::{Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value},
+    error::Error,
+    parser::parse,
+};
+use rapiere_lexer::{Lexer, TokenKind};
+use std::fmt;
+
+/// Parses a filter then renders it back into its canonical text.
+pub fn format(input: &[u8]) -> Result<String, Error> {
+    parse(input).map(|filter| filter.to_string())
+}
+
+impl fmt::Display for Filter {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(expression) = &self.expression {
+            write!(f, "{expression}")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Expression {
+    /// Binding strength of the expression, parentheses being needed around an operand
+    /// which does not bind tighter than its operator.
+    #[inline]
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Self::And(_) => 0,
+            Self::Sequence(_) => 1,
+            Self::Or(_) => 2,
+            Self::Not(_) => 3,
+            Self::Restriction(_) | Self::Error(_) => 4,
+        }
+    }
+
+    fn fmt_operands(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        operands: &[Expression],
+        separator: &str,
+    ) -> fmt::Result {
+        for (idx, operand) in operands.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "{separator}")?;
+            }
+
+            operand.fmt_operand(f, self.precedence())?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() > precedence {
+            write!(f, "{self}")
+        } else {
+            write!(f, "({self})")
+        }
+    }
+}
+
+/// Error placeholders are rendered as `<error>`, which does not parse back.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::And(operands) => self.fmt_operands(f, operands, " AND "),
+            Self::Sequence(operands) => self.fmt_operands(f, operands, " "),
+            Self::Or(operands) => self.fmt_operands(f, operands, " OR "),
+            Self::Not(operand) => {
+                write!(f, "NOT ")?;
+                operand.fmt_operand(f, self.precedence())
+            }
+            Self::Restriction(restriction) => write!(f, "{restriction}"),
+            Self::Error(_) => write!(f, "<error>"),
+        }
+    }
+}
+
+impl fmt::Display for Restriction {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.comparison {
+            Some((Comparator::Has, arg)) => write!(f, "{}:{arg}", self.comparable),
+            Some((comparator, arg)) => write!(f, "{} {comparator} {arg}", self.comparable),
+            None => write!(f, "{}", self.comparable),
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Equals => write!(f, "="),
+            Self::NotEquals => write!(f, "!="),
+            Self::LesserThan => write!(f, "<"),
+            Self::LesserThanEquals => write!(f, "<="),
+            Self::GreaterThan => write!(f, ">"),
+            Self::GreaterThanEquals => write!(f, ">="),
+            Self::Has => write!(f, ":"),
+        }
+    }
+}
+
+impl fmt::Display for Comparable {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Member(member) => write!(f, "{member}"),
+            Self::Function(function) => write!(f, "{function}"),
+        }
+    }
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+
+        for field in &self.fields {
+            if is_bare_field(field) {
+                write!(f, ".{field}")?;
+            } else {
+                write!(f, ".")?;
+                fmt_string(f, field)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Segments of the name which would not be scanned back as single words are quoted, e.g.
+/// `a."b c"(x)`, a quoted first one not parsing back as a function.
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, segment) in self.name.split('.').enumerate() {
+            if idx > 0 {
+                write!(f, ".")?;
+            }
+
+            let bare = if idx == 0 {
+                is_bare_word(segment)
+            } else {
+                is_bare_field(segment)
+            };
+
+            if bare {
+                write!(f, "{segment}")?;
+            } else {
+                fmt_string(f, segment)?;
+            }
+        }
+        write!(f, "(")?;
+
+        for (idx, arg) in self.args.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{arg}")?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for Arg {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Comparable(comparable) => write!(f, "{comparable}"),
+            Self::Composite(expression) => write!(f, "({expression})"),
+            Self::Error(_) => write!(f, "<error>"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(value) if is_bare_word(value) => write!(f, "{value}"),
+            // Quoted so that it can't be scanned back as operators, parsing as a string
+            Self::Text(value) => fmt_string(f, value),
+            Self::String(value) => fmt_string(f, value),
+            Self::Integer(value) => write!(f, "{value}"),
+            // Debug formatting keeps a fractional part or an exponent, so the value is
+            // scanned back as a float
+            Self::Float(value) => write!(f, "{value:?}"),
+            Self::Boolean(value) => write!(f, "{value}"),
+            Self::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[inline]
+fn fmt_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"{}\"", value.replace('"', "\"\""))
+}
+
+/// Whether a text is scanned back as a single identifier, hence does not need to be quoted.
+pub(crate) fn is_bare_word(text: &str) -> bool {
+    let mut lexer = Lexer::new(text.as_bytes());
+
+    match lexer.next_token() {
+        Ok(Some(token)) => token.length == text.len() && token.kind == TokenKind::Identifier,
+        _ => false,
+    }
+}
+
+/// Whether a field is scanned back as a single word, hence does not need to be quoted.
+fn is_bare_field(field: &str) -> bool {
+    let mut lexer = Lexer::new(field.as_bytes());
+
+    match lexer.next_token() {
+        Ok(Some(token)) => {
+            token.length == field.len()
+                && matches!(
+                    token.kind,
+                    TokenKind::Identifier
+                        | TokenKind::And
+                        | TokenKind::Or
+                        | TokenKind::Not
+                        | TokenKind::True
+                        | TokenKind::False
+                        | TokenKind::Null
+                )
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(b"  ", "")]
+    #[case::spacing(b"a=1  AND(b>2)", "a = 1 AND b > 2")]
+    #[case::redundant_parentheses(b"((a = 1)) AND (b > 2)", "a = 1 AND b > 2")]
+    #[case::nested_conjunction(b"(a AND b) AND c", "(a AND b) AND c")]
+    #[case::lower_precedence_operand(b"a OR (b AND c)", "a OR (b AND c)")]
+    #[case::higher_precedence_operand(b"(a OR b) c", "a OR b c")]
+    #[case::nested_sequence(b"a (b c)", "a (b c)")]
+    #[case::minus(b"-a", "NOT a")]
+    #[case::negated_composite(b"NOT(a OR b)", "NOT (a OR b)")]
+    #[case::has(b"a : b", "a:b")]
+    #[case::escaped_string(b"a!=\"foo \"\"bar\"\"\"", "a != \"foo \"\"bar\"\"\"")]
+    #[case::quoted_field(b"a.\"b\".\"c d\".\"1\"", "a.b.\"c d\".\"1\"")]
+    #[case::keyword_field(b"a.AND.true", "a.AND.true")]
+    #[case::hex_integer(b"a = 0x2A", "a = 42")]
+    #[case::separated_integer(b"a = 1_000", "a = 1000")]
+    #[case::float(b"a >= 1.50e2 OR a < -0.5E-7", "a >= 150.0 OR a < -5e-8")]
+    #[case::function(b"math.mem( a ,b ) <=1", "math.mem(a, b) <= 1")]
+    #[case::quoted_function(b"a.\"b c\"(x) = 1", "a.\"b c\"(x) = 1")]
+    #[case::keyword_function(b"a.AND.\"1\"()", "a.AND.\"1\"()")]
+    #[case::composite_argument(b"a=(1 OR 2)", "a = (1 OR 2)")]
+    fn it_formats_a_filter(#[case] input: &[u8], #[case] expected: &str) {
+        let canonical = format(input);
+        assert!(canonical.is_ok(), "{}", canonical.unwrap_err());
+
+        let canonical = canonical.unwrap();
+        assert_eq!(canonical, expected);
+
+        // The canonical text parses back into the same tree, hence formatting is idempotent
+        let filter = parse(input).unwrap();
+        assert_eq!(parse(canonical.as_bytes()).unwrap(), filter);
+        assert_eq!(format(canonical.as_bytes()).unwrap(), canonical);
+    }
+}
@@ -0,0 +1,22 @@
+mod ast;
+mod builder;
+mod error;
+pub mod fold;
+mod format;
+mod limits;
+mod parser;
+pub mod visit;
+pub mod visit_mut;
+
+pub use ast::{
+    Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value,
+};
+pub use builder::Field;
+pub use error::{Diagnostic, DiagnosticKind, Error};
+pub use fold::Fold;
+pub use format::format;
+pub use limits::Limits;
+pub use parser::{parse, Parser};
+pub use rapiere_lexer::Span;
+pub use visit::Visitor;
+pub use visit_mut::VisitorMut;
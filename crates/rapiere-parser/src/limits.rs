@@ -0,0 +1,265 @@
+//! Bounds on the resources spent on a filter.
+//!
+//! Filters usually come from untrusted sources: limits cap the size of the input, the
+//! number of tokens scanned, and the size of the tree built out of them, so that neither
+//! the stack nor the CPU can be exhausted by a crafted filter.
+
+use crate::{
+    ast::{Arg, Comparable, Expression, Filter},
+    error::Error,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    max_input_bytes: usize,
+    max_tokens: usize,
+    max_depth: usize,
+    max_restrictions: usize,
+    max_function_args: usize,
+}
+
+impl Limits {
+    pub const DEFAULT_MAX_INPUT_BYTES: usize = 64 * 1024;
+    pub const DEFAULT_MAX_TOKENS: usize = 8192;
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+    pub const DEFAULT_MAX_RESTRICTIONS: usize = 1024;
+    pub const DEFAULT_MAX_FUNCTION_ARGS: usize = 64;
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Limits which never fail, for filters coming from a trusted source.
+    #[inline]
+    pub fn unlimited() -> Self {
+        Self {
+            max_input_bytes: usize::MAX,
+            max_tokens: usize::MAX,
+            max_depth: usize::MAX,
+            max_restrictions: usize::MAX,
+            max_function_args: usize::MAX,
+        }
+    }
+
+    #[inline]
+    pub fn with_max_input_bytes(mut self, max_input_bytes: usize) -> Self {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// Caps the number of tokens scanned, whitespaces excepted.
+    #[inline]
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Caps the nesting of parentheses, either grouping expressions or enclosing the
+    /// arguments of a function.
+    #[inline]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_restrictions(mut self, max_restrictions: usize) -> Self {
+        self.max_restrictions = max_restrictions;
+        self
+    }
+
+    #[inline]
+    pub fn with_max_function_args(mut self, max_function_args: usize) -> Self {
+        self.max_function_args = max_function_args;
+        self
+    }
+
+    #[inline(always)]
+    pub fn max_input_bytes(&self) -> usize {
+        self.max_input_bytes
+    }
+
+    #[inline(always)]
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    #[inline(always)]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    #[inline(always)]
+    pub fn max_restrictions(&self) -> usize {
+        self.max_restrictions
+    }
+
+    #[inline(always)]
+    pub fn max_function_args(&self) -> usize {
+        self.max_function_args
+    }
+
+    /// Checks a tree which was not built by the parser against the limits bounding its
+    /// structure, i.e. the depth, restrictions and function arguments ones.
+    ///
+    /// The depth of a tree is the nesting of the parentheses of its canonical text. The
+    /// tree is walked without recursion, so that checking a deep tree cannot overflow the
+    /// stack.
+    pub fn check(&self, filter: &Filter) -> Result<(), Error> {
+        let Some(expression) = &filter.expression else {
+            return Ok(());
+        };
+
+        let mut restrictions = 0;
+        let mut nodes = vec![(Node::Expression(expression, None), 0)];
+
+        while let Some((node, depth)) = nodes.pop() {
+            if depth > self.max_depth {
+                return Err(Error::TooDeep {
+                    max: self.max_depth,
+                });
+            }
+
+            let comparable = match node {
+                Node::Expression(expression, parent) => {
+                    // Operands not binding tighter than their operator are parenthesized
+                    let depth = match parent {
+                        Some(precedence) if expression.precedence() <= precedence => depth + 1,
+                        _ => depth,
+                    };
+                    let precedence = Some(expression.precedence());
+
+                    match expression {
+                        Expression::And(operands)
+                        | Expression::Sequence(operands)
+                        | Expression::Or(operands) => {
+                            nodes.extend(
+                                operands
+                                    .iter()
+                                    .map(|operand| (Node::Expression(operand, precedence), depth)),
+                            );
+                            continue;
+                        }
+                        Expression::Not(operand) => {
+                            nodes.push((Node::Expression(operand, precedence), depth));
+                            continue;
+                        }
+                        Expression::Restriction(restriction) => {
+                            restrictions += 1;
+                            if restrictions > self.max_restrictions {
+                                return Err(Error::TooManyRestrictions {
+                                    max: self.max_restrictions,
+                                });
+                            }
+
+                            if let Some((_, arg)) = &restriction.comparison {
+                                nodes.push((Node::Arg(arg), depth));
+                            }
+                            (&restriction.comparable, depth)
+                        }
+                        Expression::Error(_) => continue,
+                    }
+                }
+                Node::Arg(Arg::Comparable(comparable)) => (comparable, depth),
+                Node::Arg(Arg::Composite(expression)) => {
+                    nodes.push((Node::Expression(expression, None), depth + 1));
+                    continue;
+                }
+                Node::Arg(Arg::Error(_)) => continue,
+            };
+
+            if let (Comparable::Function(function), depth) = comparable {
+                if function.args.len() > self.max_function_args {
+                    return Err(Error::TooManyFunctionArgs {
+                        name: function.name.clone(),
+                        max: self.max_function_args,
+                    });
+                }
+
+                nodes.extend(function.args.iter().map(|arg| (Node::Arg(arg), depth + 1)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            max_input_bytes: Self::DEFAULT_MAX_INPUT_BYTES,
+            max_tokens: Self::DEFAULT_MAX_TOKENS,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_restrictions: Self::DEFAULT_MAX_RESTRICTIONS,
+            max_function_args: Self::DEFAULT_MAX_FUNCTION_ARGS,
+        }
+    }
+}
+
+/// A node left to check, along with the precedence of its operator for expressions.
+enum Node<'f> {
+    Expression(&'f Expression, Option<u8>),
+    Arg(&'f Arg),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, Parser};
+    use rstest::rstest;
+
+    fn limits() -> Limits {
+        Limits::unlimited()
+            .with_max_depth(2)
+            .with_max_restrictions(3)
+            .with_max_function_args(2)
+    }
+
+    #[rstest]
+    #[case::flat(b"a = 1 AND b OR c")]
+    #[case::precedence(b"a OR (b AND c)")]
+    #[case::nested(b"NOT (a AND (b OR c))")]
+    #[case::function(b"f(x, (y))")]
+    #[case::composite(b"a = (b (c))")]
+    fn it_accepts_a_tree_within_limits(#[case] input: &[u8]) {
+        let filter = parse(input).unwrap();
+
+        assert!(limits().check(&filter).is_ok());
+        // The parser and the check agree on every limit
+        assert!(Parser::new(input).with_limits(limits()).parse().is_ok());
+    }
+
+    #[rstest]
+    #[case::depth(b"NOT (a AND NOT (b AND NOT (c AND d)))", "too deep")]
+    #[case::composite_depth(b"a = (b = (c = (1)))", "too deep")]
+    #[case::function_depth(b"f(g(h(1)))", "too deep")]
+    #[case::restrictions(b"a b c d", "restrictions")]
+    #[case::nested_restrictions(b"a = (b = (c = 1 d))", "restrictions")]
+    #[case::function_args(b"f(1, 2, 3)", "arguments")]
+    fn it_rejects_a_tree_exceeding_limits(#[case] input: &[u8], #[case] expected: &str) {
+        let filter = parse(input).unwrap();
+
+        let checked = limits().check(&filter);
+        assert!(checked.is_err());
+        assert!(checked.unwrap_err().to_string().contains(expected));
+
+        let parsed = Parser::new(input).with_limits(limits()).parse();
+        assert!(parsed.is_err());
+        assert!(parsed.unwrap_err().to_string().contains(expected));
+    }
+
+    #[test]
+    fn it_checks_a_built_tree() {
+        let mut expression = parse(b"a").unwrap().expression.unwrap();
+        for _ in 0..1000 {
+            expression = Expression::Not(Box::new(Expression::And(vec![expression])));
+        }
+
+        assert!(matches!(
+            Limits::new().check(&Filter::new(Some(expression))),
+            Err(Error::TooDeep { max: 64 })
+        ));
+    }
+}
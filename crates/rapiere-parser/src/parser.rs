@@ -0,0 +1,760 @@
+use crate::{
+    ast::{Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value},
+    error::{Diagnostic, DiagnosticKind, Error},
+    limits::Limits,
+};
+use rapiere_lexer::{Lexer, Span, Token, TokenKind, TokenValue};
+
+/// Parses a whole filter within the default limits, failing with every syntax error found
+/// in the input.
+pub fn parse(input: &[u8]) -> Result<Filter, Error> {
+    let (filter, diagnostics) = Parser::new(input).parse()?;
+
+    if diagnostics.is_empty() {
+        Ok(filter)
+    } else {
+        Err(Error::Syntax(diagnostics))
+    }
+}
+
+/// A token, stripped of the whitespaces preceding it.
+#[derive(Clone, Debug)]
+struct Lexeme {
+    kind: TokenKind,
+    span: Span,
+
+    /// Whether whitespaces separate the lexeme from the previous one
+    spaced: bool,
+
+    value: Option<Value>,
+}
+
+/// Recursive descent parser of AIP-160 filters.
+///
+/// The parser does not stop at the first syntax error. Invalid tokens are reported and
+/// skipped, while any unexpected token is reported and skipped along with the following
+/// ones until either `)`, `AND`, `OR` or the end of the input is reached. The part of the
+/// filter which could not be parsed is replaced by an `Error` node in the tree.
+///
+/// Exceeding a limit, on the other hand, stops the parser right away.
+#[derive(Debug)]
+pub struct Parser<'i> {
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
+
+    /// First limit exceeded by the input
+    exceeded: Option<Error>,
+
+    input: &'i [u8],
+    lexemes: Vec<Lexeme>,
+    limits: Limits,
+    position: usize,
+    restrictions: usize,
+}
+
+impl<'i> Parser<'i> {
+    #[inline(always)]
+    pub fn new(input: &'i [u8]) -> Self {
+        Self {
+            depth: 0,
+            diagnostics: Vec::new(),
+            exceeded: None,
+            input,
+            lexemes: Vec::new(),
+            limits: Limits::default(),
+            position: 0,
+            restrictions: 0,
+        }
+    }
+
+    #[inline]
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Parses the input into a filter, which holds `Error` nodes wherever the returned
+    /// diagnostics are located, only failing when a limit is exceeded.
+    pub fn parse(mut self) -> Result<(Filter, Vec<Diagnostic>), Error> {
+        if self.input.len() > self.limits.max_input_bytes() {
+            return Err(Error::InputTooLarge {
+                bytes: self.input.len(),
+                max: self.limits.max_input_bytes(),
+            });
+        }
+
+        self.scan();
+        if let Some(err) = self.exceeded {
+            return Err(err);
+        }
+
+        self.skip_unmatched();
+        let expression = if self.at(TokenKind::EOF) {
+            None
+        } else {
+            Some(self.expression())
+        };
+        debug_assert!(self.at(TokenKind::EOF), "filter should be parsed entirely");
+
+        if let Some(err) = self.exceeded {
+            return Err(err);
+        }
+
+        self.diagnostics
+            .sort_by_key(|diagnostic| diagnostic.span.offset);
+        Ok((Filter::new(expression), self.diagnostics))
+    }
+
+    fn scan(&mut self) {
+        let mut lexer = Lexer::new(self.input);
+        let mut spaced = false;
+
+        loop {
+            match lexer.next_token() {
+                Ok(Some(token)) => {
+                    if matches!(token.kind, TokenKind::Whitespace | TokenKind::NewLine) {
+                        spaced = true;
+                        continue;
+                    }
+
+                    let span = token.span();
+                    let lexeme = match self.value(&token) {
+                        Ok(value) => Lexeme {
+                            kind: token.kind,
+                            span,
+                            spaced,
+                            value,
+                        },
+                        Err(kind) => {
+                            self.report(kind, span);
+
+                            Lexeme {
+                                kind: TokenKind::Undefined,
+                                span,
+                                spaced,
+                                value: None,
+                            }
+                        }
+                    };
+                    self.lexemes.push(lexeme);
+                    spaced = false;
+
+                    if token.kind == TokenKind::EOF {
+                        break;
+                    }
+                    if self.lexemes.len() > self.limits.max_tokens() {
+                        self.exceed(Error::TooManyTokens {
+                            max: self.limits.max_tokens(),
+                        });
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    let (offset, line, column) = (lexer.offset(), lexer.line(), lexer.column());
+                    let length = lexer.skip_invalid_token();
+                    let span = Span::new(offset, length, line, column);
+
+                    self.report(DiagnosticKind::InvalidToken(err.message()), span);
+                    self.lexemes.push(Lexeme {
+                        kind: TokenKind::Undefined,
+                        span,
+                        spaced,
+                        value: None,
+                    });
+                    spaced = false;
+
+                    if self.lexemes.len() > self.limits.max_tokens() {
+                        self.exceed(Error::TooManyTokens {
+                            max: self.limits.max_tokens(),
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn value(&self, token: &Token) -> Result<Option<Value>, DiagnosticKind> {
+        let value = match token.kind {
+            TokenKind::Identifier => Value::Text(self.text(token.span())),
+            TokenKind::True => Value::Boolean(true),
+            TokenKind::False => Value::Boolean(false),
+            TokenKind::Null => Value::Null,
+            TokenKind::Literal => {
+                let text = self.text(token.span());
+
+                if text.starts_with('"') {
+                    Value::String(text[1..text.len() - 1].replace("\"\"", "\""))
+                } else {
+                    match token.value {
+                        Some(TokenValue::Integer(value)) => Value::Integer(value),
+                        Some(TokenValue::Float(value)) if value.is_finite() => Value::Float(value),
+                        // Separated digits, as well as numbers overflowing their type, are
+                        // left unparsed by the lexer
+                        _ => number(&text.replace('_', ""))
+                            .ok_or(DiagnosticKind::NumberOutOfRange)?,
+                    }
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(value))
+    }
+
+    fn expression(&mut self) -> Expression {
+        let mut operands = vec![self.sequence()];
+
+        while self.at(TokenKind::And) {
+            self.bump();
+            operands.push(self.sequence());
+        }
+
+        join(operands, Expression::And)
+    }
+
+    fn sequence(&mut self) -> Expression {
+        let mut operands = vec![self.factor()];
+
+        loop {
+            self.skip_unmatched();
+
+            if matches!(
+                self.peek().kind,
+                TokenKind::And | TokenKind::Or | TokenKind::RightParenthesis | TokenKind::EOF
+            ) {
+                break;
+            }
+            operands.push(self.factor());
+        }
+
+        join(operands, Expression::Sequence)
+    }
+
+    fn factor(&mut self) -> Expression {
+        let mut operands = vec![self.term()];
+
+        while self.at(TokenKind::Or) {
+            self.bump();
+            operands.push(self.term());
+        }
+
+        join(operands, Expression::Or)
+    }
+
+    fn term(&mut self) -> Expression {
+        self.skip_unmatched();
+
+        if matches!(self.peek().kind, TokenKind::Not | TokenKind::Minus) {
+            self.bump();
+
+            Expression::Not(Box::new(self.simple()))
+        } else {
+            self.simple()
+        }
+    }
+
+    fn simple(&mut self) -> Expression {
+        if self.at(TokenKind::LeftParenthesis) {
+            let open = self.open();
+            let expression = self.expression();
+            self.close(&open);
+
+            expression
+        } else {
+            self.restriction()
+        }
+    }
+
+    fn restriction(&mut self) -> Expression {
+        let start = self.peek().span;
+        let comparable = match self.comparable("expression") {
+            Ok(comparable) => comparable,
+            Err(span) => return Expression::Error(span),
+        };
+
+        let comparison = if let Some(comparator) = comparator(self.peek().kind) {
+            self.bump();
+
+            Some((comparator, self.arg()))
+        } else {
+            None
+        };
+
+        self.restrictions += 1;
+        if self.restrictions > self.limits.max_restrictions() {
+            self.exceed(Error::TooManyRestrictions {
+                max: self.limits.max_restrictions(),
+            });
+        }
+
+        let span = start.to(self.lexemes[self.position - 1].span);
+        Expression::Restriction(Restriction::new(span, comparable, comparison))
+    }
+
+    fn comparable(&mut self, expected: &'static str) -> Result<Comparable, Span> {
+        let Some(value) = self.peek().value.clone() else {
+            return Err(self.recover(expected));
+        };
+        let start = self.bump().span;
+
+        let mut fields = Vec::new();
+        while self.at(TokenKind::Dot) {
+            self.bump();
+
+            if let Some(field) = self.field() {
+                fields.push(field);
+            } else {
+                return Err(start.to(self.recover("field")));
+            }
+        }
+
+        match value {
+            Value::Text(name) if self.at(TokenKind::LeftParenthesis) && !self.peek().spaced => {
+                let name = std::iter::once(name).chain(fields).collect::<Vec<_>>();
+                let open = self.open();
+
+                let mut args = Vec::new();
+                if !self.at(TokenKind::RightParenthesis) {
+                    loop {
+                        args.push(self.arg());
+
+                        if args.len() > self.limits.max_function_args() {
+                            self.exceed(Error::TooManyFunctionArgs {
+                                name: name.join("."),
+                                max: self.limits.max_function_args(),
+                            });
+                        }
+                        if !self.at(TokenKind::Comma) {
+                            break;
+                        }
+                        self.bump();
+                    }
+                }
+                self.close(&open);
+
+                Ok(Comparable::Function(Function::new(name.join("."), args)))
+            }
+            value => Ok(Comparable::Member(Member::new(value, fields))),
+        }
+    }
+
+    fn field(&mut self) -> Option<String> {
+        let lexeme = self.peek();
+
+        match (lexeme.kind, &lexeme.value) {
+            (_, Some(Value::String(field))) => {
+                let field = field.clone();
+                self.bump();
+
+                Some(field)
+            }
+            (
+                TokenKind::Identifier
+                | TokenKind::Literal
+                | TokenKind::And
+                | TokenKind::Or
+                | TokenKind::Not
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null,
+                _,
+            ) => {
+                let span = self.bump().span;
+
+                Some(self.text(span))
+            }
+            _ => None,
+        }
+    }
+
+    fn arg(&mut self) -> Arg {
+        if self.at(TokenKind::LeftParenthesis) {
+            let open = self.open();
+            let expression = self.expression();
+            self.close(&open);
+
+            return Arg::Composite(Box::new(expression));
+        }
+
+        match self.comparable("argument") {
+            Ok(comparable) => Arg::Comparable(comparable),
+            Err(span) => Arg::Error(span),
+        }
+    }
+
+    #[inline]
+    fn open(&mut self) -> Lexeme {
+        self.depth += 1;
+        let open = self.bump();
+
+        if self.depth > self.limits.max_depth() {
+            self.exceed(Error::TooDeep {
+                max: self.limits.max_depth(),
+            });
+        }
+
+        open
+    }
+
+    fn close(&mut self, open: &Lexeme) {
+        match self.peek().kind {
+            TokenKind::RightParenthesis => {
+                self.bump();
+            }
+            TokenKind::EOF => self.report(DiagnosticKind::UnclosedParenthesis, open.span),
+            _ => {
+                self.recover("`)`");
+
+                if self.at(TokenKind::RightParenthesis) {
+                    self.bump();
+                }
+            }
+        }
+
+        self.depth -= 1;
+    }
+
+    /// Reports and skips closing parentheses which do not match any opening one, the
+    /// operands following them being parsed as if they were not there.
+    fn skip_unmatched(&mut self) {
+        while self.depth == 0 && self.at(TokenKind::RightParenthesis) {
+            let span = self.bump().span;
+            self.report(DiagnosticKind::UnmatchedParenthesis, span);
+        }
+    }
+
+    /// Reports the current token as unexpected, then skips every token until a
+    /// synchronisation point is reached, returning the span of the skipped tokens.
+    ///
+    /// Closing parentheses are only synchronisation points when one is expected.
+    fn recover(&mut self, expected: &'static str) -> Span {
+        let found = self.peek();
+        let span = found.span;
+
+        // Invalid tokens have already been reported while scanning the input
+        if found.kind != TokenKind::Undefined {
+            let found = found.kind;
+            self.report(DiagnosticKind::UnexpectedToken { expected, found }, span);
+        }
+
+        let mut skipped = Span::new(span.offset, 0, span.line, span.column);
+        loop {
+            match self.peek().kind {
+                TokenKind::RightParenthesis if self.depth > 0 => break,
+                TokenKind::And | TokenKind::Or | TokenKind::EOF => break,
+                _ => skipped = skipped.to(self.bump().span),
+            }
+        }
+
+        skipped
+    }
+
+    #[inline(always)]
+    fn at(&self, kind: TokenKind) -> bool {
+        self.peek().kind == kind
+    }
+
+    /// Moves to the next lexeme, the last one being always the end of the input.
+    #[inline]
+    fn bump(&mut self) -> Lexeme {
+        let lexeme = self.lexemes[self.position].clone();
+
+        if lexeme.kind != TokenKind::EOF {
+            self.position += 1;
+        }
+
+        lexeme
+    }
+
+    #[inline(always)]
+    fn peek(&self) -> &Lexeme {
+        &self.lexemes[self.position]
+    }
+
+    /// Records the first limit exceeded, then moves to the end of the input so that the
+    /// parser unwinds without consuming any other token.
+    #[cold]
+    fn exceed(&mut self, err: Error) {
+        self.exceeded.get_or_insert(err);
+        self.position = self.lexemes.len() - 1;
+    }
+
+    #[inline(always)]
+    fn report(&mut self, kind: DiagnosticKind, span: Span) {
+        self.diagnostics.push(Diagnostic::new(kind, span));
+    }
+
+    #[inline(always)]
+    fn text(&self, span: Span) -> String {
+        String::from_utf8_lossy(&self.input[span.offset..span.end()]).into_owned()
+    }
+}
+
+#[inline]
+fn comparator(kind: TokenKind) -> Option<Comparator> {
+    match kind {
+        TokenKind::Equals => Some(Comparator::Equals),
+        TokenKind::NotEquals => Some(Comparator::NotEquals),
+        TokenKind::LesserThan => Some(Comparator::LesserThan),
+        TokenKind::LesserThanEquals => Some(Comparator::LesserThanEquals),
+        TokenKind::GreaterThan => Some(Comparator::GreaterThan),
+        TokenKind::GreaterThanEquals => Some(Comparator::GreaterThanEquals),
+        TokenKind::Colon => Some(Comparator::Has),
+        _ => None,
+    }
+}
+
+#[inline]
+fn join(mut operands: Vec<Expression>, operator: fn(Vec<Expression>) -> Expression) -> Expression {
+    if operands.len() == 1 {
+        operands.pop().expect("operands hold a single expression")
+    } else {
+        operator(operands)
+    }
+}
+
+fn number(text: &str) -> Option<Value> {
+    let (sign, digits) = if let Some(digits) = text.strip_prefix('-') {
+        (-1, digits)
+    } else {
+        (1, text)
+    };
+
+    if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)
+            .ok()
+            .map(|value| Value::Integer(sign * value))
+    } else if text.contains(['.', 'e', 'E']) {
+        text.parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .map(Value::Float)
+    } else {
+        text.parse::<i64>().ok().map(Value::Integer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn member(value: Value, fields: &[&str]) -> Comparable {
+        Comparable::Member(Member::new(
+            value,
+            fields.iter().map(|field| field.to_string()).collect(),
+        ))
+    }
+
+    fn text(value: &str) -> Comparable {
+        member(Value::Text(value.to_owned()), &[])
+    }
+
+    fn global(comparable: Comparable) -> Expression {
+        Expression::Restriction(Restriction::new(Span::default(), comparable, None))
+    }
+
+    fn compare(comparable: Comparable, comparator: Comparator, arg: Arg) -> Expression {
+        Expression::Restriction(Restriction::new(
+            Span::default(),
+            comparable,
+            Some((comparator, arg)),
+        ))
+    }
+
+    fn literal(value: Value) -> Arg {
+        Arg::Comparable(member(value, &[]))
+    }
+
+    #[rstest]
+    #[case::empty(b"", None)]
+    #[case::whitespaces(b" \n\t", None)]
+    #[case::global_restriction(b"prod", Some(global(text("prod"))))]
+    #[case::comparison(
+        b"a = 42",
+        Some(compare(text("a"), Comparator::Equals, literal(Value::Integer(42))))
+    )]
+    #[case::member_traversal(
+        b"a.b.\"c d\" != \"foo \"\"bar\"\"\"",
+        Some(compare(
+            member(Value::Text("a".to_owned()), &["b", "c d"]),
+            Comparator::NotEquals,
+            literal(Value::String("foo \"bar\"".to_owned())),
+        )),
+    )]
+    #[case::keyword_field(
+        b"a.NOT:true",
+        Some(compare(
+            member(Value::Text("a".to_owned()), &["NOT"]),
+            Comparator::Has,
+            literal(Value::Boolean(true)),
+        )),
+    )]
+    #[case::separated_integer(
+        b"a >= 1_000",
+        Some(compare(
+            text("a"),
+            Comparator::GreaterThanEquals,
+            literal(Value::Integer(1000))
+        ))
+    )]
+    #[case::float(
+        b"a < -1.5e3",
+        Some(compare(text("a"), Comparator::LesserThan, literal(Value::Float(-1500.0)))),
+    )]
+    #[case::or_binds_tighter_than_and(
+        b"a AND b OR c",
+        Some(Expression::And(vec![
+            global(text("a")),
+            Expression::Or(vec![global(text("b")), global(text("c"))]),
+        ])),
+    )]
+    #[case::sequence(
+        b"a b OR c",
+        Some(Expression::Sequence(vec![
+            global(text("a")),
+            Expression::Or(vec![global(text("b")), global(text("c"))]),
+        ])),
+    )]
+    #[case::grouping(
+        b"(a AND b) OR -c",
+        Some(Expression::Or(vec![
+            Expression::And(vec![global(text("a")), global(text("b"))]),
+            Expression::Not(Box::new(global(text("c")))),
+        ])),
+    )]
+    #[case::negation(
+        b"NOT a <= null",
+        Some(Expression::Not(Box::new(compare(
+            text("a"),
+            Comparator::LesserThanEquals,
+            literal(Value::Null),
+        ))))
+    )]
+    #[case::function(
+        b"math.mem(a, 0x2A) > 1",
+        Some(compare(
+            Comparable::Function(Function::new(
+                "math.mem",
+                vec![Arg::Comparable(text("a")), literal(Value::Integer(42))],
+            )),
+            Comparator::GreaterThan,
+            literal(Value::Integer(1)),
+        )),
+    )]
+    #[case::spaced_parenthesis_is_not_a_call(
+        b"a (b)",
+        Some(Expression::Sequence(vec![global(text("a")), global(text("b"))])),
+    )]
+    #[case::composite_argument(
+        b"a = (1 OR 2)",
+        Some(compare(
+            text("a"),
+            Comparator::Equals,
+            Arg::Composite(Box::new(Expression::Or(vec![
+                global(member(Value::Integer(1), &[])),
+                global(member(Value::Integer(2), &[])),
+            ]))),
+        )),
+    )]
+    fn it_parses_a_filter(#[case] input: &[u8], #[case] expected: Option<Expression>) {
+        let filter = parse(input);
+
+        assert!(filter.is_ok(), "{}", filter.unwrap_err());
+        assert_eq!(filter.unwrap().expression, expected);
+    }
+
+    #[rstest]
+    #[case::missing_argument(b"a = 1 AND b >", &[(1, 14)])]
+    #[case::missing_operand(b"a AND AND b", &[(1, 7)])]
+    #[case::unclosed_parenthesis(b"(a = 1", &[(1, 1)])]
+    #[case::unmatched_parenthesis(b"a = 1) AND b", &[(1, 6)])]
+    #[case::leading_unmatched_parenthesis(b") a", &[(1, 1)])]
+    #[case::lone_unmatched_parenthesis(b")", &[(1, 1)])]
+    #[case::unclosed_call(b"f(a b) AND c", &[(1, 5)])]
+    #[case::invalid_tokens(b"a = ! AND b = 1__2", &[(1, 5), (1, 15)])]
+    #[case::bad_field(b"a.= 1 OR b.(", &[(1, 3), (1, 12)])]
+    #[case::out_of_range(b"a = 99999999999999999999", &[(1, 5)])]
+    #[case::every_error(b"(a = 1 AND b > ) OR c = \"x", &[(1, 16), (1, 25)])]
+    fn it_reports_every_syntax_error(#[case] input: &[u8], #[case] expected: &[(u64, u64)]) {
+        let (_, diagnostics) = Parser::new(input).parse().unwrap();
+
+        let positions = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.span.line, diagnostic.span.column))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn it_recovers_a_partial_tree() {
+        let (filter, diagnostics) = Parser::new(b"(a = 1 AND b > ) OR c = \"x").parse().unwrap();
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::new(
+                    DiagnosticKind::UnexpectedToken {
+                        expected: "argument",
+                        found: TokenKind::RightParenthesis,
+                    },
+                    Span::new(15, 1, 1, 16),
+                ),
+                Diagnostic::new(
+                    DiagnosticKind::InvalidToken("non-terminated string literal"),
+                    Span::new(24, 2, 1, 25),
+                ),
+            ]
+        );
+        assert_eq!(
+            filter.expression,
+            Some(Expression::Or(vec![
+                Expression::And(vec![
+                    compare(text("a"), Comparator::Equals, literal(Value::Integer(1))),
+                    compare(
+                        text("b"),
+                        Comparator::GreaterThan,
+                        Arg::Error(Span::new(15, 0, 1, 16)),
+                    ),
+                ]),
+                compare(
+                    text("c"),
+                    Comparator::Equals,
+                    Arg::Error(Span::new(24, 2, 1, 25)),
+                ),
+            ]))
+        );
+    }
+
+    #[rstest]
+    #[case::input_bytes(Limits::new().with_max_input_bytes(8), b"a = 1 AND b = 2", "15 bytes")]
+    #[case::tokens(Limits::new().with_max_tokens(4), b"a = 1 AND b", "4 tokens")]
+    #[case::invalid_tokens(Limits::new().with_max_tokens(2), b"! ! !", "2 tokens")]
+    #[case::depth(Limits::new().with_max_depth(3), b"f((((a))))", "3 levels")]
+    #[case::restrictions(Limits::new().with_max_restrictions(2), b"a b AND c", "2 restrictions")]
+    #[case::function_args(Limits::new().with_max_function_args(1), b"f(a, b)", "`f`")]
+    fn it_enforces_limits(#[case] limits: Limits, #[case] input: &[u8], #[case] expected: &str) {
+        let parsed = Parser::new(input).with_limits(limits).parse();
+
+        assert!(parsed.is_err());
+        assert!(parsed.unwrap_err().to_string().contains(expected));
+    }
+
+    #[test]
+    fn it_records_the_restriction_span() {
+        let filter = parse(b"a AND\n  b.c = \"d\"").unwrap();
+
+        let Some(Expression::And(operands)) = filter.expression else {
+            panic!("filter should be a conjunction");
+        };
+        let Expression::Restriction(restriction) = &operands[1] else {
+            panic!("operand should be a restriction");
+        };
+        assert_eq!(restriction.span, Span::new(8, 9, 2, 3));
+    }
+}
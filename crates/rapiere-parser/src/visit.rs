@@ -0,0 +1,180 @@
+//! Read-only traversal of a filter's tree.
+//!
+//! Every method of [`Visitor`] defaults to walking through the node's children with the
+//! matching `walk_*` function, so an implementation only overrides the methods of the
+//! nodes it is interested in, calling the `walk_*` function to keep on traversing them.
+
+use crate::ast::{
+    Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value,
+};
+use rapiere_lexer::Span;
+
+pub trait Visitor {
+    fn visit_filter(&mut self, filter: &Filter) {
+        walk_filter(self, filter);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_and(&mut self, operands: &[Expression]) {
+        walk_operands(self, operands);
+    }
+
+    fn visit_sequence(&mut self, operands: &[Expression]) {
+        walk_operands(self, operands);
+    }
+
+    fn visit_or(&mut self, operands: &[Expression]) {
+        walk_operands(self, operands);
+    }
+
+    fn visit_not(&mut self, operand: &Expression) {
+        self.visit_expression(operand);
+    }
+
+    fn visit_restriction(&mut self, restriction: &Restriction) {
+        walk_restriction(self, restriction);
+    }
+
+    fn visit_comparable(&mut self, comparable: &Comparable) {
+        walk_comparable(self, comparable);
+    }
+
+    fn visit_member(&mut self, member: &Member) {
+        walk_member(self, member);
+    }
+
+    fn visit_field(&mut self, _field: &str) {}
+
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_comparator(&mut self, _comparator: Comparator) {}
+
+    fn visit_arg(&mut self, arg: &Arg) {
+        walk_arg(self, arg);
+    }
+
+    /// Visits a parenthesized expression used as an argument.
+    fn visit_composite(&mut self, expression: &Expression) {
+        self.visit_expression(expression);
+    }
+
+    fn visit_value(&mut self, _value: &Value) {}
+
+    /// Visits the placeholder of either an expression or an argument which could not be
+    /// parsed.
+    fn visit_error(&mut self, _span: Span) {}
+}
+
+pub fn walk_filter<V: Visitor + ?Sized>(visitor: &mut V, filter: &Filter) {
+    if let Some(expression) = &filter.expression {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::And(operands) => visitor.visit_and(operands),
+        Expression::Sequence(operands) => visitor.visit_sequence(operands),
+        Expression::Or(operands) => visitor.visit_or(operands),
+        Expression::Not(operand) => visitor.visit_not(operand),
+        Expression::Restriction(restriction) => visitor.visit_restriction(restriction),
+        Expression::Error(span) => visitor.visit_error(*span),
+    }
+}
+
+pub fn walk_operands<V: Visitor + ?Sized>(visitor: &mut V, operands: &[Expression]) {
+    for operand in operands {
+        visitor.visit_expression(operand);
+    }
+}
+
+pub fn walk_restriction<V: Visitor + ?Sized>(visitor: &mut V, restriction: &Restriction) {
+    visitor.visit_comparable(&restriction.comparable);
+
+    if let Some((comparator, arg)) = &restriction.comparison {
+        visitor.visit_comparator(*comparator);
+        visitor.visit_arg(arg);
+    }
+}
+
+pub fn walk_comparable<V: Visitor + ?Sized>(visitor: &mut V, comparable: &Comparable) {
+    match comparable {
+        Comparable::Member(member) => visitor.visit_member(member),
+        Comparable::Function(function) => visitor.visit_function(function),
+    }
+}
+
+pub fn walk_member<V: Visitor + ?Sized>(visitor: &mut V, member: &Member) {
+    visitor.visit_value(&member.value);
+
+    for field in &member.fields {
+        visitor.visit_field(field);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &Function) {
+    for arg in &function.args {
+        visitor.visit_arg(arg);
+    }
+}
+
+pub fn walk_arg<V: Visitor + ?Sized>(visitor: &mut V, arg: &Arg) {
+    match arg {
+        Arg::Comparable(comparable) => visitor.visit_comparable(comparable),
+        Arg::Composite(expression) => visitor.visit_composite(expression),
+        Arg::Error(span) => visitor.visit_error(*span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    /// Collects the path of every member compared to an argument.
+    #[derive(Default)]
+    struct Audit {
+        paths: Vec<String>,
+        functions: usize,
+    }
+
+    impl Visitor for Audit {
+        fn visit_restriction(&mut self, restriction: &Restriction) {
+            if let (Comparable::Member(member), Some(_)) =
+                (&restriction.comparable, &restriction.comparison)
+            {
+                self.paths.push(member.to_string());
+            }
+
+            walk_restriction(self, restriction);
+        }
+
+        fn visit_function(&mut self, function: &Function) {
+            self.functions += 1;
+            walk_function(self, function);
+        }
+    }
+
+    #[rstest]
+    #[case::empty(b"", &[], 0)]
+    #[case::global(b"a", &[], 0)]
+    #[case::nested(b"a.b = 1 AND NOT (c > 2 OR d:e)", &["a.b", "c", "d"], 0)]
+    #[case::composite(b"a = (b = 1 OR 2) f(g.h)", &["a", "b"], 1)]
+    fn it_visits_every_node(
+        #[case] input: &[u8],
+        #[case] expected_paths: &[&str],
+        #[case] expected_functions: usize,
+    ) {
+        let mut audit = Audit::default();
+        audit.visit_filter(&parse(input).unwrap());
+
+        assert_eq!(audit.paths, expected_paths);
+        assert_eq!(audit.functions, expected_functions);
+    }
+}
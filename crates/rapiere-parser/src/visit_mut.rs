@@ -0,0 +1,166 @@
+//! In-place traversal of a filter's tree.
+//!
+//! This is the mutable counterpart of [`crate::visit`], for passes which edit nodes
+//! without changing their kind, e.g. renaming fields.
+
+use crate::ast::{
+    Arg, Comparable, Comparator, Expression, Filter, Function, Member, Restriction, Value,
+};
+use rapiere_lexer::Span;
+
+pub trait VisitorMut {
+    fn visit_filter_mut(&mut self, filter: &mut Filter) {
+        walk_filter_mut(self, filter);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_and_mut(&mut self, operands: &mut Vec<Expression>) {
+        walk_operands_mut(self, operands);
+    }
+
+    fn visit_sequence_mut(&mut self, operands: &mut Vec<Expression>) {
+        walk_operands_mut(self, operands);
+    }
+
+    fn visit_or_mut(&mut self, operands: &mut Vec<Expression>) {
+        walk_operands_mut(self, operands);
+    }
+
+    fn visit_not_mut(&mut self, operand: &mut Expression) {
+        self.visit_expression_mut(operand);
+    }
+
+    fn visit_restriction_mut(&mut self, restriction: &mut Restriction) {
+        walk_restriction_mut(self, restriction);
+    }
+
+    fn visit_comparable_mut(&mut self, comparable: &mut Comparable) {
+        walk_comparable_mut(self, comparable);
+    }
+
+    fn visit_member_mut(&mut self, member: &mut Member) {
+        walk_member_mut(self, member);
+    }
+
+    fn visit_field_mut(&mut self, _field: &mut String) {}
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_comparator_mut(&mut self, _comparator: &mut Comparator) {}
+
+    fn visit_arg_mut(&mut self, arg: &mut Arg) {
+        walk_arg_mut(self, arg);
+    }
+
+    /// Visits a parenthesized expression used as an argument.
+    fn visit_composite_mut(&mut self, expression: &mut Expression) {
+        self.visit_expression_mut(expression);
+    }
+
+    fn visit_value_mut(&mut self, _value: &mut Value) {}
+
+    /// Visits the placeholder of either an expression or an argument which could not be
+    /// parsed.
+    fn visit_error_mut(&mut self, _span: &mut Span) {}
+}
+
+pub fn walk_filter_mut<V: VisitorMut + ?Sized>(visitor: &mut V, filter: &mut Filter) {
+    if let Some(expression) = &mut filter.expression {
+        visitor.visit_expression_mut(expression);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::And(operands) => visitor.visit_and_mut(operands),
+        Expression::Sequence(operands) => visitor.visit_sequence_mut(operands),
+        Expression::Or(operands) => visitor.visit_or_mut(operands),
+        Expression::Not(operand) => visitor.visit_not_mut(operand),
+        Expression::Restriction(restriction) => visitor.visit_restriction_mut(restriction),
+        Expression::Error(span) => visitor.visit_error_mut(span),
+    }
+}
+
+pub fn walk_operands_mut<V: VisitorMut + ?Sized>(visitor: &mut V, operands: &mut [Expression]) {
+    for operand in operands {
+        visitor.visit_expression_mut(operand);
+    }
+}
+
+pub fn walk_restriction_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    restriction: &mut Restriction,
+) {
+    visitor.visit_comparable_mut(&mut restriction.comparable);
+
+    if let Some((comparator, arg)) = &mut restriction.comparison {
+        visitor.visit_comparator_mut(comparator);
+        visitor.visit_arg_mut(arg);
+    }
+}
+
+pub fn walk_comparable_mut<V: VisitorMut + ?Sized>(visitor: &mut V, comparable: &mut Comparable) {
+    match comparable {
+        Comparable::Member(member) => visitor.visit_member_mut(member),
+        Comparable::Function(function) => visitor.visit_function_mut(function),
+    }
+}
+
+pub fn walk_member_mut<V: VisitorMut + ?Sized>(visitor: &mut V, member: &mut Member) {
+    visitor.visit_value_mut(&mut member.value);
+
+    for field in &mut member.fields {
+        visitor.visit_field_mut(field);
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut Function) {
+    for arg in &mut function.args {
+        visitor.visit_arg_mut(arg);
+    }
+}
+
+pub fn walk_arg_mut<V: VisitorMut + ?Sized>(visitor: &mut V, arg: &mut Arg) {
+    match arg {
+        Arg::Comparable(comparable) => visitor.visit_comparable_mut(comparable),
+        Arg::Composite(expression) => visitor.visit_composite_mut(expression),
+        Arg::Error(span) => visitor.visit_error_mut(span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use rstest::rstest;
+
+    /// Renames the root of member paths, e.g. a legacy field exposed under a new name.
+    struct Rename(&'static str, &'static str);
+
+    impl VisitorMut for Rename {
+        fn visit_member_mut(&mut self, member: &mut Member) {
+            if member.value == Value::Text(self.0.to_owned()) {
+                member.value = Value::Text(self.1.to_owned());
+            }
+
+            walk_member_mut(self, member);
+        }
+    }
+
+    #[rstest]
+    #[case::restriction(b"old = 1", "new = 1")]
+    #[case::traversal(b"old.old:x", "new.old:x")]
+    #[case::argument(b"a = old OR f(old.b) > (old)", "a = new OR f(new.b) > (new)")]
+    #[case::string_value(b"a = \"old\"", "a = \"old\"")]
+    fn it_edits_nodes_in_place(#[case] input: &[u8], #[case] expected: &str) {
+        let mut filter = parse(input).unwrap();
+        Rename("old", "new").visit_filter_mut(&mut filter);
+
+        assert_eq!(filter.to_string(), expected);
+    }
+}
@@ -0,0 +1,60 @@
+//! AIP-160 filtering, from the text of a filter down to its evaluation.
+//!
+//! Filters known ahead of time can be checked at compile time with [`filter!`]:
+//!
+//! ```
+//! let filter = rapiere::filter!("state = ACTIVE AND create_time > \"2024-01-01T00:00:00Z\"");
+//!
+//! assert_eq!(
+//!     filter.to_string(),
+//!     "state = ACTIVE AND create_time > \"2024-01-01T00:00:00Z\""
+//! );
+//! ```
+//!
+//! Syntax errors are then compile errors:
+//!
+//! ```compile_fail
+//! let filter = rapiere::filter!("state = AND create_time >");
+//! ```
+
+// Lets the expansion of macros refer to this crate from within it
+extern crate self as rapiere;
+
+pub use rapiere_compiler as compiler;
+pub use rapiere_lexer as lexer;
+pub use rapiere_macros::filter;
+pub use rapiere_parser as parser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(filter!(" "), "")]
+    #[case::restriction(filter!("a.b = 42"), "a.b = 42")]
+    #[case::operators(filter!("a AND (b c OR NOT d)"), "a AND (b c OR NOT d)")]
+    #[case::values(
+        filter!("a = \"x \"\"y\"\"\" OR a = -1.5 OR a = -0x2A OR a != null OR a.\"b c\":true"),
+        "a = \"x \"\"y\"\"\" OR a = -1.5 OR a = -0x2A OR a != null OR a.\"b c\":true"
+    )]
+    #[case::function(filter!("math.mem(a, (1 OR 2)) >= 3"), "math.mem(a, (1 OR 2)) >= 3")]
+    fn it_expands_to_the_parsed_filter(#[case] filter: parser::Filter, #[case] input: &str) {
+        let parsed = parser::parse(input.as_bytes()).unwrap();
+
+        assert_eq!(filter, parsed);
+    }
+
+    #[test]
+    fn it_keeps_the_restriction_span() {
+        let filter = filter!("a AND\n  b.c = \"d\"");
+
+        let Some(parser::Expression::And(operands)) = filter.expression else {
+            panic!("filter should be a conjunction");
+        };
+        let parser::Expression::Restriction(restriction) = &operands[1] else {
+            panic!("operand should be a restriction");
+        };
+        assert_eq!(restriction.span, parser::Span::new(8, 9, 2, 3));
+    }
+}
@@ -1,6 +1,11 @@
+use crate::models::plan::Component;
 use clap::{command, Args, Parser, Subcommand};
 use rand::RngCore;
-use std::{borrow::Cow, path::PathBuf};
+use std::{
+    borrow::Cow,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
 #[derive(Parser)]
 #[command(name = "simulator", about, propagate_version = true, version)]
@@ -11,9 +16,12 @@ pub(crate) struct Cli {
 
 impl Cli {
     #[inline(always)]
-    pub(crate) fn seed(&self) -> u64 {
+    pub(crate) fn seed(&self) -> Option<u64> {
         match &self.command {
-            Commands::Lexer(args) => args.seed(),
+            Commands::Lexer(args) => Some(args.seed()),
+            Commands::BenchLexer(args) => Some(args.seed()),
+            Commands::Oracle(args) => Some(args.seed()),
+            Commands::Replay(_) | Commands::Disassemble(_) => None,
         }
     }
 }
@@ -22,6 +30,18 @@ impl Cli {
 pub(crate) enum Commands {
     /// Run the simulator to test `rapiere-lexer` crate
     Lexer(CommandArgs),
+
+    /// Measure the throughput of `rapiere-lexer` crate over a generated corpus
+    BenchLexer(BenchArgs),
+
+    /// Replay a saved simulation plan against the component under test
+    Replay(ReplayArgs),
+
+    /// Print the registers and instructions a filter compiles to
+    Disassemble(DisassembleArgs),
+
+    /// Check compiled filters against the interpreter on generated filters and records
+    Oracle(OracleArgs),
 }
 
 #[derive(Args)]
@@ -35,6 +55,36 @@ pub(crate) struct CommandArgs {
         help = "Path used to save the simulation plan"
     )]
     plan_path: Option<Cow<'static, str>>,
+
+    #[arg(
+        short = 'r',
+        long = "runs",
+        help = "Number of seeds to simulate as a campaign, derived from the seed"
+    )]
+    runs: Option<u64>,
+
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        help = "Number of worker threads used by a campaign"
+    )]
+    jobs: Option<usize>,
+
+    #[arg(long = "report", help = "Path used to save the campaign report")]
+    report_path: Option<Cow<'static, str>>,
+
+    #[arg(
+        short = 'm',
+        long = "mutate",
+        help = "Corrupt the generated input and check the reported error position"
+    )]
+    mutate: bool,
+
+    #[arg(
+        long = "profile",
+        help = "Path of a TOML, or JSON, profile steering the generators"
+    )]
+    profile_path: Option<PathBuf>,
 }
 
 impl CommandArgs {
@@ -55,4 +105,174 @@ impl CommandArgs {
             PathBuf::from("plan.txt")
         }
     }
+
+    #[inline(always)]
+    pub(crate) fn mutate(&self) -> bool {
+        self.mutate
+    }
+
+    #[inline(always)]
+    pub(crate) fn profile_path(&self) -> Option<&Path> {
+        self.profile_path.as_deref()
+    }
+
+    #[inline(always)]
+    pub(crate) fn runs(&self) -> Option<u64> {
+        self.runs
+    }
+
+    #[inline(always)]
+    pub(crate) fn jobs(&self) -> usize {
+        if let Some(jobs) = self.jobs {
+            jobs.max(1)
+        } else {
+            std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn report_path(&self) -> PathBuf {
+        if let Some(path) = &self.report_path {
+            PathBuf::from(path.as_ref())
+        } else {
+            PathBuf::from("report.json")
+        }
+    }
+}
+
+#[derive(Args)]
+pub(crate) struct ReplayArgs {
+    #[arg(help = "Path of the simulation plan to replay")]
+    pub(crate) plan: PathBuf,
+
+    #[arg(
+        short = 'c',
+        long = "component",
+        default_value = "lexer",
+        help = "Component fed with raw plans, structured plans name their own"
+    )]
+    pub(crate) component: Component,
+}
+
+#[derive(Args)]
+pub(crate) struct DisassembleArgs {
+    #[arg(
+        required_unless_present = "encoded",
+        help = "Filter to compile, e.g. `create_time > \"2024-01-01T00:00:00Z\"`"
+    )]
+    pub(crate) filter: Option<String>,
+
+    #[arg(
+        short = 'e',
+        long = "encoded",
+        conflicts_with = "filter",
+        help = "Path of a binary encoded filter to disassemble instead"
+    )]
+    pub(crate) encoded: Option<PathBuf>,
+
+    #[arg(
+        short = 'O',
+        long = "optimize",
+        help = "Run the optimization passes over the filter before disassembling it"
+    )]
+    pub(crate) optimize: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct OracleArgs {
+    #[arg(help = "Seed of the generated filters and records")]
+    seed: Option<u64>,
+
+    #[arg(
+        short = 'n',
+        long = "cases",
+        default_value_t = 10_000,
+        help = "Number of filters generated, each one with a record of its own"
+    )]
+    pub(crate) cases: u64,
+
+    #[arg(
+        short = 'd',
+        long = "depth",
+        default_value_t = 4,
+        help = "Maximum nesting of the operators of generated filters"
+    )]
+    pub(crate) depth: usize,
+}
+
+impl OracleArgs {
+    #[inline(always)]
+    fn seed(&self) -> u64 {
+        if let Some(seed) = &self.seed {
+            *seed
+        } else {
+            rand::rng().next_u64()
+        }
+    }
+}
+
+#[derive(Args)]
+pub(crate) struct BenchArgs {
+    #[arg(help = "Seed of the generated corpus")]
+    seed: Option<u64>,
+
+    #[arg(
+        short = 'n',
+        long = "inputs",
+        default_value_t = 256,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Number of inputs in the generated corpus"
+    )]
+    pub(crate) inputs: u64,
+
+    #[arg(
+        short = 'i',
+        long = "iterations",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Number of measured scans of the whole corpus"
+    )]
+    pub(crate) iterations: u64,
+
+    #[arg(
+        long = "warmup",
+        default_value_t = 3,
+        help = "Number of unmeasured scans of the whole corpus"
+    )]
+    pub(crate) warmup: u64,
+
+    #[arg(long = "report", help = "Path used to save the benchmark report")]
+    pub(crate) report: Option<PathBuf>,
+
+    #[arg(
+        short = 'b',
+        long = "baseline",
+        help = "Path of a saved benchmark report to compare against"
+    )]
+    pub(crate) baseline: Option<PathBuf>,
+
+    #[arg(
+        short = 't',
+        long = "threshold",
+        default_value_t = 5.0,
+        help = "Regression, in percent of the baseline median throughput, failing the run"
+    )]
+    pub(crate) threshold: f64,
+
+    #[arg(
+        long = "profile",
+        help = "Path of a TOML, or JSON, profile steering the generators"
+    )]
+    pub(crate) profile_path: Option<PathBuf>,
+}
+
+impl BenchArgs {
+    #[inline(always)]
+    fn seed(&self) -> u64 {
+        if let Some(seed) = &self.seed {
+            *seed
+        } else {
+            rand::rng().next_u64()
+        }
+    }
 }
@@ -0,0 +1,220 @@
+use crate::{
+    cli::BenchArgs,
+    error::Error,
+    generation::{Arbitrary, Profile},
+    models::{
+        bench::{BenchReport, Percentiles},
+        lexer::RawInput,
+    },
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rapiere_lexer::Lexer;
+use std::{
+    fs::File,
+    hint,
+    time::{Duration, Instant},
+};
+
+/// Measures the lexer throughput over a corpus generated from `seed`.
+///
+/// Every iteration scans the whole corpus, and only the time spent in
+/// [`Lexer::next_token`] is accounted for. Throughputs are then compared against the
+/// baseline report, if any, the run failing when the median one regressed more than the
+/// allowed threshold.
+pub(crate) fn entrypoint(seed: u64, args: BenchArgs, profile: &Profile) -> Result<(), Error> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let corpus = (0..args.inputs)
+        .map(|_| RawInput::arbitrary(&mut rng, profile).as_bytes())
+        .collect::<Vec<_>>();
+    let bytes = corpus.iter().map(|input| input.len() as u64).sum::<u64>();
+
+    tracing::info!(
+        inputs = corpus.len(),
+        bytes = bytes,
+        "benchmark corpus generated"
+    );
+
+    for _ in 0..args.warmup {
+        scan(&corpus);
+    }
+
+    let mut tokens = 0;
+    let mut tokens_per_sec = Vec::with_capacity(args.iterations as usize);
+    let mut bytes_per_sec = Vec::with_capacity(args.iterations as usize);
+    for iteration in 0..args.iterations {
+        let (scanned, elapsed) = scan(&corpus);
+        let elapsed = elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+
+        tracing::debug!(
+            iteration = iteration,
+            tokens = scanned,
+            elapsed_ms = elapsed * 1e3,
+            "benchmark iteration done"
+        );
+
+        tokens = scanned;
+        tokens_per_sec.push(scanned as f64 / elapsed);
+        bytes_per_sec.push(bytes as f64 / elapsed);
+    }
+
+    let report = BenchReport {
+        seed,
+        inputs: corpus.len(),
+        bytes,
+        tokens,
+        iterations: args.iterations,
+        tokens_per_sec: Percentiles::of(&mut tokens_per_sec),
+        bytes_per_sec: Percentiles::of(&mut bytes_per_sec),
+    };
+
+    tracing::info!(
+        tokens = report.tokens,
+        p50 = report.tokens_per_sec.p50,
+        p90 = report.tokens_per_sec.p90,
+        p99 = report.tokens_per_sec.p99,
+        "tokens per second"
+    );
+    tracing::info!(
+        bytes = report.bytes,
+        p50 = report.bytes_per_sec.p50,
+        p90 = report.bytes_per_sec.p90,
+        p99 = report.bytes_per_sec.p99,
+        "bytes per second"
+    );
+
+    if let Some(path) = &args.report {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &report).map_err(std::io::Error::from)?;
+        tracing::info!(path = %path.display(), "benchmark report saved");
+    }
+
+    if let Some(path) = &args.baseline {
+        let baseline: BenchReport =
+            serde_json::from_reader(File::open(path)?).map_err(std::io::Error::from)?;
+        if baseline.seed != report.seed || baseline.bytes != report.bytes {
+            tracing::warn!(
+                baseline_seed = baseline.seed,
+                baseline_bytes = baseline.bytes,
+                "baseline was measured over a different corpus"
+            );
+        }
+
+        compare(
+            "tokens_per_sec",
+            baseline.tokens_per_sec.p50,
+            report.tokens_per_sec.p50,
+            args.threshold,
+        )?;
+        compare(
+            "bytes_per_sec",
+            baseline.bytes_per_sec.p50,
+            report.bytes_per_sec.p50,
+            args.threshold,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Scans every input of the corpus, returning the number of tokens and the time spent
+/// producing them.
+fn scan(corpus: &[Vec<u8>]) -> (u64, Duration) {
+    let mut tokens = 0;
+    let mut elapsed = Duration::ZERO;
+
+    for input in corpus {
+        let mut lexer = Lexer::new(input);
+        let start = Instant::now();
+
+        // Errors only end the input early, the corpus being the same across iterations
+        while let Ok(Some(token)) = lexer.next_token() {
+            hint::black_box(token);
+            tokens += 1;
+        }
+
+        elapsed += start.elapsed();
+    }
+
+    (tokens, elapsed)
+}
+
+/// Fails when `current` regressed more than `threshold` percent of `baseline`, which must
+/// be a positive throughput for the regression to be meaningful.
+fn compare(metric: &'static str, baseline: f64, current: f64, threshold: f64) -> Result<(), Error> {
+    // Also rejects NaN, against which any regression would pass
+    if !(baseline.is_finite() && baseline > 0.0) {
+        return Err(Error::Baseline { metric, baseline });
+    }
+
+    let regression = (baseline - current) / baseline * 100.0;
+
+    tracing::info!(
+        metric = metric,
+        baseline = baseline,
+        current = current,
+        change = -regression,
+        "compared against baseline"
+    );
+
+    if regression > threshold {
+        return Err(Error::Regression {
+            metric,
+            regression,
+            threshold,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::unchanged(100.0, 100.0)]
+    #[case::improvement(100.0, 150.0)]
+    #[case::below_threshold(100.0, 96.0)]
+    #[case::at_threshold(100.0, 95.0)]
+    fn it_accepts_a_throughput_within_the_threshold(#[case] baseline: f64, #[case] current: f64) {
+        assert!(compare("tokens_per_sec", baseline, current, 5.0).is_ok());
+    }
+
+    #[rstest]
+    #[case::above_threshold(100.0, 94.0, 6.0)]
+    #[case::collapse(100.0, 0.0, 100.0)]
+    fn it_rejects_a_regression(#[case] baseline: f64, #[case] current: f64, #[case] expected: f64) {
+        let err = compare("tokens_per_sec", baseline, current, 5.0).unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                Error::Regression { metric: "tokens_per_sec", regression, threshold: 5.0 }
+                    if (regression - expected).abs() < 1e-9
+            ),
+            "{err}"
+        );
+    }
+
+    #[rstest]
+    #[case::zero(0.0)]
+    #[case::negative(-1.0)]
+    #[case::nan(f64::NAN)]
+    #[case::infinite(f64::INFINITY)]
+    fn it_rejects_a_non_positive_baseline(#[case] baseline: f64) {
+        let err = compare("bytes_per_sec", baseline, 100.0, 5.0).unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                Error::Baseline {
+                    metric: "bytes_per_sec",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
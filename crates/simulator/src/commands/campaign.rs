@@ -0,0 +1,126 @@
+use crate::{
+    error::Error,
+    models::{
+        plan::Component,
+        report::{CampaignReport, Failure},
+    },
+};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::{
+    fs::File,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+/// Runs `runs` simulations of `component` spread across `jobs` worker threads.
+///
+/// The seed of every run is derived from the campaign `seed`, so that a whole campaign can
+/// be reproduced, and any failing run can be reproduced on its own from its seed.
+pub(crate) fn entrypoint<F>(
+    component: Component,
+    seed: u64,
+    runs: u64,
+    jobs: usize,
+    report_path: &Path,
+    check: F,
+) -> Result<(), Error>
+where
+    F: Fn(u64) -> Option<Failure> + Sync,
+{
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let seeds = (0..runs).map(|_| rng.next_u64()).collect::<Vec<_>>();
+
+    tracing::info!(runs = runs, jobs = jobs, "starting simulation campaign");
+
+    let start = Instant::now();
+    let next = AtomicU64::new(0);
+    let failures = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let run = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&seed) = seeds.get(run as usize) else {
+                        break;
+                    };
+
+                    // `check` already catches the panics of the component under test, this
+                    // only guards the worker against the simulation's own bugs
+                    let failure = panic::catch_unwind(AssertUnwindSafe(|| check(seed)))
+                        .unwrap_or_else(|_| {
+                            Some(Failure {
+                                seed,
+                                outcome: "panic".to_owned(),
+                                message: Some("simulation panicked".to_owned()),
+                                reproducer: String::new(),
+                            })
+                        });
+
+                    if let Some(failure) = failure {
+                        tracing::error!(seed = seed, outcome = %failure.outcome, "run failed");
+                        failures
+                            .lock()
+                            .expect("campaign failures lock is poisoned")
+                            .push((run, failure));
+                    } else {
+                        tracing::debug!(seed = seed, "run passed");
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failures = failures
+        .into_inner()
+        .expect("campaign failures lock is poisoned");
+    failures.sort_by_key(|(run, _)| *run);
+
+    let failed = failures.len() as u64;
+    let report = CampaignReport {
+        component,
+        seed,
+        runs,
+        jobs,
+        passed: runs - failed,
+        failed,
+        duration_ms: start.elapsed().as_millis(),
+        failures: failures.into_iter().map(|(_, failure)| failure).collect(),
+    };
+
+    tracing::info!(
+        runs = report.runs,
+        passed = report.passed,
+        failed = report.failed,
+        duration_ms = report.duration_ms as u64,
+        "simulation campaign complete"
+    );
+    for failure in &report.failures {
+        tracing::error!(
+            seed = failure.seed,
+            outcome = %failure.outcome,
+            reproducer = %failure.reproducer,
+            "failing seed"
+        );
+    }
+
+    let file = File::create(report_path)?;
+    serde_json::to_writer_pretty(file, &report).map_err(std::io::Error::from)?;
+    tracing::info!(path = %report_path.display(), "campaign report saved");
+
+    if report.failed > 0 {
+        Err(Error::Campaign {
+            failed: report.failed,
+            runs: report.runs,
+        })
+    } else {
+        Ok(())
+    }
+}
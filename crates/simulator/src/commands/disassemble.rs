@@ -0,0 +1,34 @@
+use crate::{cli::DisassembleArgs, error::Error};
+use rapiere_compiler::{Compiler, Filter, Optimizer};
+use rapiere_parser::Parser;
+
+pub(crate) fn entrypoint(args: DisassembleArgs) -> Result<(), Error> {
+    if let Some(path) = &args.encoded {
+        tracing::info!(path = %path.display(), "disassembling encoded filter");
+        let mut filter = Filter::from_bytes(&std::fs::read(path)?)?;
+        if args.optimize {
+            filter = Optimizer::new().optimize(filter);
+        }
+
+        print!("{filter}");
+        return Ok(());
+    }
+
+    let input = args.filter.unwrap_or_default();
+    let (filter, diagnostics) = Parser::new(input.as_bytes())
+        .parse()
+        .map_err(rapiere_compiler::Error::from)?;
+    for diagnostic in &diagnostics {
+        tracing::error!(diagnostic = %diagnostic, "unable to parse filter");
+    }
+
+    let mut compiler = Compiler::new();
+    if args.optimize {
+        compiler = compiler.with_optimizer(Optimizer::new());
+    }
+
+    let filter = compiler.compile(filter)?;
+    print!("{}", filter.disassemble(input.as_bytes()));
+
+    Ok(())
+}
@@ -1,32 +1,252 @@
-use crate::{cli::CommandArgs, generation::Arbitrary, models::lexer::RawInput};
+use crate::{
+    cli::CommandArgs,
+    error::Error,
+    generation::{Arbitrary, Profile},
+    models::{
+        lexer::{InvalidInput, RawInput},
+        plan::{self, Component, Plan},
+        report,
+    },
+    shrinking::{self, Shrink},
+};
 use rand::SeedableRng;
 use rand_chacha::ChaCha8Rng;
-use rapiere_lexer::{Error, Lexer};
-use std::{fs::File, io::Write};
+use rapiere_lexer::Lexer;
+use std::{
+    any::Any,
+    fs::File,
+    io::Write,
+    mem::{self, Discriminant},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+/// Kind of failure a simulation ended with, used to check a shrunk input still fails the
+/// same way as the original one.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    Error(Discriminant<rapiere_lexer::Error>),
+    Panic,
+}
+
+type Outcome = Result<Result<(), rapiere_lexer::Error>, Box<dyn Any + Send>>;
+
+impl Failure {
+    fn of(outcome: &Outcome) -> Option<Self> {
+        match outcome {
+            Ok(Ok(_)) => None,
+            Ok(Err(err)) => Some(Self::Error(mem::discriminant(err))),
+            Err(_) => Some(Self::Panic),
+        }
+    }
+}
+
+pub(crate) fn entrypoint(seed: u64, args: CommandArgs, profile: &Profile) -> Result<(), Error> {
+    if args.mutate() {
+        return entrypoint_invalid(seed, args, profile);
+    }
 
-pub(crate) fn entrypoint(seed: u64, args: CommandArgs) -> Result<(), Error> {
     let mut rng = ChaCha8Rng::seed_from_u64(seed);
 
     tracing::info!("generating raw input");
-    let raw_input = RawInput::arbitrary(&mut rng);
+    let raw_input = RawInput::arbitrary(&mut rng, profile);
 
     tracing::info!("generation done");
     tracing::debug!(raw = %raw_input, "generated raw input");
 
-    let raw_input = raw_input.as_bytes();
-    let mut scanner = Lexer::new(&raw_input);
+    let plan_path = args.plan_path();
+    save_plan(&plan_path, &raw_input.as_bytes());
+
+    let outcome = run(&raw_input);
+    if let Some(failure) = Failure::of(&outcome) {
+        tracing::info!(
+            fragments = raw_input.fragments().len(),
+            "shrinking failing input"
+        );
+
+        let minimal = shrink(&raw_input, &failure);
+        let minimal_path = plan_path.with_extension("min.txt");
+        save_plan(&minimal_path, &minimal.as_bytes());
+
+        tracing::error!(
+            seed = seed,
+            fragments = minimal.fragments().len(),
+            path = %minimal_path.display(),
+            reproducer = %minimal,
+            "minimal reproducer saved"
+        );
+    }
+
+    match outcome {
+        Ok(output) => Ok(output?),
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Runs the lexer against a corrupted input, checking the expected error is reported at
+/// the corrupted fragment's position.
+fn entrypoint_invalid(seed: u64, args: CommandArgs, profile: &Profile) -> Result<(), Error> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    tracing::info!("generating invalid input");
+    let invalid_input = InvalidInput::arbitrary(&mut rng, profile);
+    let expected = invalid_input.expectation();
+
+    tracing::info!(mutation = ?invalid_input.mutation.kind, "generation done");
+    tracing::debug!(raw = %invalid_input, expect = %expected, "generated invalid input");
+
+    let plan_path = args.plan_path();
+    save_structured_plan(&plan_path, seed, &invalid_input);
+
+    let outcome = run_bytes(&invalid_input.as_bytes());
+    let actual = observe(&outcome);
+    if expected.matches(&actual) {
+        tracing::info!(outcome = %actual, "expected error reported");
+        return Ok(());
+    }
+
+    tracing::info!(
+        fragments = invalid_input.prefix.fragments().len(),
+        "shrinking failing input"
+    );
+
+    let minimal = shrink_invalid(&invalid_input, &actual);
+    let minimal_path = plan_path.with_extension("min.txt");
+    save_structured_plan(&minimal_path, seed, &minimal);
+
+    tracing::error!(
+        seed = seed,
+        fragments = minimal.prefix.fragments().len(),
+        path = %minimal_path.display(),
+        reproducer = %minimal,
+        "minimal reproducer saved"
+    );
+
+    match outcome {
+        Err(payload) => panic::resume_unwind(payload),
+        Ok(_) => Err(Error::Mismatch { expected, actual }),
+    }
+}
+
+/// Runs the simulation of a single campaign seed, without saving any plan.
+pub(crate) fn check(seed: u64, mutate: bool, profile: &Profile) -> Option<report::Failure> {
+    if mutate {
+        return check_invalid(seed, profile);
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let raw_input = RawInput::arbitrary(&mut rng, profile);
+
+    let outcome = run(&raw_input);
+    let failure = Failure::of(&outcome)?;
+    let minimal = shrink(&raw_input, &failure);
+
+    Some(report::Failure {
+        seed,
+        outcome: observe(&outcome).to_string(),
+        message: message(&outcome),
+        reproducer: minimal.to_string(),
+    })
+}
+
+fn check_invalid(seed: u64, profile: &Profile) -> Option<report::Failure> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let invalid_input = InvalidInput::arbitrary(&mut rng, profile);
+    let expected = invalid_input.expectation();
+
+    let outcome = run_bytes(&invalid_input.as_bytes());
+    let actual = observe(&outcome);
+    if expected.matches(&actual) {
+        return None;
+    }
+
+    let minimal = shrink_invalid(&invalid_input, &actual);
+
+    Some(report::Failure {
+        seed,
+        outcome: format!("expected {expected}, got {actual}"),
+        message: message(&outcome),
+        reproducer: minimal.to_string(),
+    })
+}
+
+/// Feeds a saved plan's input to the lexer.
+#[inline(always)]
+pub(crate) fn replay(input: &[u8]) -> plan::Outcome {
+    observe(&run_bytes(input))
+}
+
+fn message(outcome: &Outcome) -> Option<String> {
+    match outcome {
+        Ok(Ok(_)) => None,
+        Ok(Err(err)) => Some(err.to_string()),
+        Err(payload) => payload.downcast_ref::<String>().cloned().or_else(|| {
+            payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+        }),
+    }
+}
 
-    let mut file = File::create(args.plan_path()).expect("unable to save raw input");
-    file.write_all(&raw_input)
-        .expect("unable to save raw input");
+fn observe(outcome: &Outcome) -> plan::Outcome {
+    match outcome {
+        Ok(Ok(_)) => plan::Outcome::Success,
+        Ok(Err(err)) => plan::Outcome::from(err),
+        Err(_) => plan::Outcome::Panic,
+    }
+}
+
+#[inline(always)]
+fn run(raw_input: &RawInput<'_>) -> Outcome {
+    run_bytes(&raw_input.as_bytes())
+}
 
-    loop {
-        if let Some(token) = scanner.next_token()? {
+fn run_bytes(raw_input: &[u8]) -> Outcome {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut scanner = Lexer::new(raw_input);
+
+        while let Some(token) = scanner.next_token()? {
             tracing::trace!(token = %token, "token scanned")
-        } else {
-            break;
         }
-    }
 
-    Ok(())
+        Ok(())
+    }))
+}
+
+fn shrink<'i>(raw_input: &RawInput<'i>, failure: &Failure) -> RawInput<'i> {
+    shrinking::shrinking(|| {
+        raw_input.shrink(|candidate| Failure::of(&run(candidate)).as_ref() == Some(failure))
+    })
+}
+
+/// Shrinks an invalid input while the lexer keeps missing its expectation with the same
+/// kind of outcome.
+fn shrink_invalid<'i>(
+    invalid_input: &InvalidInput<'i>,
+    actual: &plan::Outcome,
+) -> InvalidInput<'i> {
+    shrinking::shrinking(|| {
+        invalid_input.shrink(|candidate| {
+            let outcome = observe(&run_bytes(&candidate.as_bytes()));
+
+            !candidate.expectation().matches(&outcome) && outcome.same_kind(actual)
+        })
+    })
+}
+
+fn save_plan(path: &Path, raw_input: &[u8]) {
+    let mut file = File::create(path).expect("unable to save raw input");
+    file.write_all(raw_input).expect("unable to save raw input");
+}
+
+fn save_structured_plan(path: &Path, seed: u64, invalid_input: &InvalidInput<'_>) {
+    let plan = Plan {
+        component: Component::Lexer,
+        seed: Some(seed),
+        input: invalid_input.to_string(),
+        expect: invalid_input.expectation(),
+    };
+
+    let file = File::create(path).expect("unable to save simulation plan");
+    serde_json::to_writer_pretty(file, &plan).expect("unable to save simulation plan");
 }
@@ -1,13 +1,61 @@
-use crate::cli::Commands;
-use rapiere_lexer::Error;
+use crate::{cli::Commands, error::Error, generation::Profile, models::plan::Component};
+use std::path::Path;
 
+mod bench;
+mod campaign;
+mod disassemble;
 mod lexer;
+mod oracle;
+mod replay;
 
-pub(crate) fn run_command(seed: u64, command: Commands) -> Result<(), Error> {
+pub(crate) fn run_command(seed: Option<u64>, command: Commands) -> Result<(), Error> {
     match command {
         Commands::Lexer(args) => {
             tracing::info!("running rapiere-lexer simulation");
-            lexer::entrypoint(seed, args)
+            let seed = seed.expect("lexer simulations are always seeded");
+            let profile = load_profile(args.profile_path())?;
+
+            if let Some(runs) = args.runs() {
+                let mutate = args.mutate();
+
+                campaign::entrypoint(
+                    Component::Lexer,
+                    seed,
+                    runs,
+                    args.jobs(),
+                    &args.report_path(),
+                    |seed| lexer::check(seed, mutate, &profile),
+                )
+            } else {
+                lexer::entrypoint(seed, args, &profile)
+            }
+        }
+        Commands::BenchLexer(args) => {
+            tracing::info!("running rapiere-lexer benchmark");
+            let seed = seed.expect("lexer benchmarks are always seeded");
+            let profile = load_profile(args.profile_path.as_deref())?;
+
+            bench::entrypoint(seed, args, &profile)
+        }
+        Commands::Replay(args) => {
+            tracing::info!(plan = %args.plan.display(), "replaying simulation plan");
+            replay::entrypoint(args)
+        }
+        Commands::Disassemble(args) => disassemble::entrypoint(args),
+        Commands::Oracle(args) => {
+            tracing::info!("checking rapiere-compiler against its interpreter");
+            let seed = seed.expect("oracle simulations are always seeded");
+
+            oracle::entrypoint(seed, args)
         }
     }
 }
+
+fn load_profile(path: Option<&Path>) -> Result<Profile, Error> {
+    let Some(path) = path else {
+        return Ok(Profile::default());
+    };
+
+    tracing::info!(profile = %path.display(), "loading generation profile");
+    Profile::load(path).map_err(Error::Profile)
+}
@@ -0,0 +1,194 @@
+use crate::{
+    cli::OracleArgs,
+    error::Error,
+    generation::filter::{random_filter, random_record, LENGTH_FUNCTION},
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rapiere_compiler::{
+    interpret, interpret_leniently, Compiler, EvalContext, EvalError, Literal, Optimizer, Record,
+    Value,
+};
+use std::{
+    borrow::Cow,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// A generated record, supporting the function generated filters call.
+struct Sample(Value);
+
+impl Record for Sample {
+    #[inline]
+    fn get(&self, path: &str) -> Cow<'_, Value> {
+        self.0.get(path)
+    }
+
+    #[inline]
+    fn has(&self, path: &str, arg: &Literal) -> bool {
+        Record::has(&self.0, path, arg)
+    }
+
+    fn call(&self, function: &str, args: &[Value]) -> Result<Value, EvalError> {
+        let length = match (function, args) {
+            (LENGTH_FUNCTION, [Value::List(values)]) => values.len(),
+            (LENGTH_FUNCTION, [Value::Map(entries)]) => entries.len(),
+            (LENGTH_FUNCTION, [Value::Literal(Literal::String(value))]) => value.chars().count(),
+            (LENGTH_FUNCTION, [_]) => 0,
+            (LENGTH_FUNCTION, _) => {
+                return Err(EvalError::Function {
+                    name: function.to_owned(),
+                    message: format!("expected a single argument, got {}", args.len()),
+                });
+            }
+            _ => return Err(EvalError::UnknownFunction(function.to_owned())),
+        };
+
+        Ok(Value::Literal(Literal::Integer(length as i64)))
+    }
+}
+
+/// Results the interpreter expects a compiled filter to evaluate to.
+#[derive(Clone, Copy, Debug)]
+enum Expected {
+    Matches(bool),
+
+    /// Some operands fail, which the compiled filter may short-circuit
+    MatchesOrFails(bool),
+
+    /// Operands the result depends on fail
+    Fails,
+
+    /// The filter holds a comparison within a composite argument, which compilers reject
+    Rejected,
+}
+
+impl Expected {
+    fn of(filter: &rapiere_parser::Filter, record: &Sample) -> Self {
+        match interpret(filter, record) {
+            Ok(matches) => return Self::Matches(matches),
+            Err(EvalError::NestedComparison { .. }) => return Self::Rejected,
+            Err(_) => {}
+        }
+
+        match interpret_leniently(filter, record) {
+            Ok(matches) => Self::MatchesOrFails(matches),
+            Err(_) => Self::Fails,
+        }
+    }
+
+    #[inline]
+    fn allows(&self, actual: &Result<bool, EvalError>) -> bool {
+        match (self, actual) {
+            (Self::Matches(expected) | Self::MatchesOrFails(expected), Ok(matches)) => {
+                expected == matches
+            }
+            (Self::MatchesOrFails(_) | Self::Fails, Err(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Matches(matches) => write!(f, "{matches}"),
+            Self::MatchesOrFails(matches) => write!(f, "{matches} or an error"),
+            Self::Fails => f.write_str("an error"),
+            Self::Rejected => f.write_str("the filter to be rejected"),
+        }
+    }
+}
+
+/// Checks that the compiled filters, optimized or not, evaluate the generated records as
+/// the interpreter does.
+///
+/// The interpreter evaluating every operand, the compiled filters may only disagree with it
+/// when it fails, short-circuiting the operands it failed on: they must then fail as well
+/// unless the operands evaluated successfully decide the result, and never panic.
+pub(crate) fn entrypoint(seed: u64, args: OracleArgs) -> Result<(), Error> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let compilers = [
+        ("plain", Compiler::new()),
+        (
+            "optimized",
+            Compiler::new().with_optimizer(Optimizer::new()),
+        ),
+    ];
+    let mut context = EvalContext::new();
+    let (mut checked, mut failed, mut rejected) = (0, 0, 0);
+
+    for case in 0..args.cases {
+        let input = random_filter(&mut rng, args.depth);
+        let record = Sample(random_record(&mut rng));
+        tracing::debug!(case = case, filter = %input, "generated filter");
+
+        let filter =
+            rapiere_parser::parse(input.as_bytes()).map_err(rapiere_compiler::Error::from)?;
+        let expected = Expected::of(&filter, &record);
+        match expected {
+            Expected::Matches(_) => {}
+            Expected::Rejected => rejected += 1,
+            _ => {
+                tracing::debug!(case = case, expected = %expected, "interpreter failed");
+                failed += 1;
+            }
+        }
+
+        for (name, compiler) in &compilers {
+            let compiled = match (compiler.compile(filter.clone()), expected) {
+                (Ok(compiled), _) => compiled,
+                (Err(rapiere_compiler::Error::NestedComparison { .. }), Expected::Rejected) => {
+                    continue;
+                }
+                (Err(err @ rapiere_compiler::Error::NestedComparison { .. }), _) => {
+                    return Err(Error::Disagreement {
+                        filter: input,
+                        expected: expected.to_string(),
+                        actual: err.to_string(),
+                    });
+                }
+                // Filters whose normal form exceeds the limits are left out
+                (Err(err @ rapiere_compiler::Error::NormalFormTooLarge { .. }), _) => {
+                    tracing::debug!(case = case, error = %err, "filter not compiled");
+                    break;
+                }
+                (Err(err), _) => return Err(err.into()),
+            };
+            let actual = panic::catch_unwind(AssertUnwindSafe(|| {
+                compiled.evaluate(&mut context, &record)
+            }));
+
+            if !actual.as_ref().is_ok_and(|actual| expected.allows(actual)) {
+                tracing::error!(
+                    case = case,
+                    compiler = name,
+                    record = ?record.0,
+                    program = %compiled,
+                    "compiled filter disagrees with the interpreter"
+                );
+
+                return Err(Error::Disagreement {
+                    filter: input,
+                    expected: expected.to_string(),
+                    actual: match actual {
+                        Ok(Ok(matches)) => matches.to_string(),
+                        Ok(Err(err)) => err.to_string(),
+                        Err(_) => "a panic".to_owned(),
+                    },
+                });
+            }
+        }
+
+        checked += 1;
+    }
+
+    tracing::info!(
+        checked = checked,
+        interpreter_failures = failed,
+        rejected = rejected,
+        "compiled filters agree with the interpreter"
+    );
+
+    Ok(())
+}
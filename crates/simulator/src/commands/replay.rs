@@ -0,0 +1,48 @@
+use super::lexer;
+use crate::{
+    cli::ReplayArgs,
+    error::Error,
+    models::plan::{Component, Expectation, Plan},
+};
+
+pub(crate) fn entrypoint(args: ReplayArgs) -> Result<(), Error> {
+    let content = std::fs::read(&args.plan)?;
+
+    // Anything which isn't a JSON object is considered to be the raw input saved by a
+    // simulation run, which is expected to go through the component without failing.
+    let (component, input, expected) = if is_structured(&content) {
+        let plan = serde_json::from_slice::<Plan>(&content)?;
+        tracing::info!(
+            component = ?plan.component,
+            seed = plan.seed,
+            expect = %plan.expect,
+            "replaying structured plan"
+        );
+
+        (plan.component, plan.input.into_bytes(), plan.expect)
+    } else {
+        tracing::info!(component = ?args.component, "replaying raw plan");
+
+        (args.component, content, Expectation::Success)
+    };
+
+    let actual = match component {
+        Component::Lexer => lexer::replay(&input),
+    };
+
+    if expected.matches(&actual) {
+        tracing::info!(outcome = %actual, "replayed plan behaved as expected");
+        Ok(())
+    } else {
+        Err(Error::Mismatch { expected, actual })
+    }
+}
+
+/// Whether the content is a structured plan, i.e. a JSON object.
+#[inline]
+fn is_structured(content: &[u8]) -> bool {
+    content
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'{')
+}
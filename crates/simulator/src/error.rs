@@ -0,0 +1,50 @@
+use crate::models::plan::{Expectation, Outcome};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum Error {
+    #[error(transparent)]
+    Lexer(#[from] rapiere_lexer::Error),
+
+    #[error(transparent)]
+    Compiler(#[from] rapiere_compiler::Error),
+
+    #[error("invalid encoded filter: {0}")]
+    Decode(#[from] rapiere_compiler::DecodeError),
+
+    #[error("{failed} out of {runs} simulation runs failed")]
+    Campaign { failed: u64, runs: u64 },
+
+    #[error("unable to access simulation file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid simulation plan: {0}")]
+    Plan(#[from] serde_json::Error),
+
+    #[error("invalid generation profile: {0}")]
+    Profile(String),
+
+    #[error("baseline {metric} of {baseline} is not a positive throughput")]
+    Baseline { metric: &'static str, baseline: f64 },
+
+    #[error("{metric} regressed by {regression:.2}%, above the {threshold}% threshold")]
+    Regression {
+        metric: &'static str,
+        regression: f64,
+        threshold: f64,
+    },
+
+    #[error(
+        "compiled filter `{filter}` evaluated to {actual} where the interpreter expected {expected}"
+    )]
+    Disagreement {
+        filter: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("simulation plan did not behave as expected: expected {expected}, got {actual}")]
+    Mismatch {
+        expected: Expectation,
+        actual: Outcome,
+    },
+}
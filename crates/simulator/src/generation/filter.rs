@@ -0,0 +1,203 @@
+use super::pick;
+use rand::Rng;
+use rapiere_compiler::{Literal, Value};
+use std::collections::BTreeMap;
+
+/// Fields of the generated records, which the generated filters refer to.
+const FIELDS: &[&str] = &["name", "size", "ratio", "state", "tags", "meta", "created"];
+
+/// Strings of the generated records, also compared against as literals of the filters.
+const STRINGS: &[&str] = &[
+    "",
+    "rapiere",
+    "rust",
+    "ACTIVE",
+    "2024-01-01T00:00:00Z",
+    "2025-06-15T12:30:00+02:00",
+    "3s",
+    "1.5s",
+];
+
+/// Literals of the generated filters, as written in their source.
+const LITERALS: &[&str] = &[
+    "0",
+    "1",
+    "2",
+    "3",
+    "1.5",
+    "true",
+    "false",
+    "null",
+    "ACTIVE",
+    "PAUSED",
+    "\"\"",
+    "\"rapiere\"",
+    "\"rust\"",
+    "\"rap*\"",
+    "\"*\"",
+    "\"ACTIVE\"",
+    "\"2024-01-01T00:00:00Z\"",
+    "\"2025-01-01T00:00:00Z\"",
+    "\"3s\"",
+    "\"2.5s\"",
+];
+
+const COMPARATORS: &[&str] = &["=", "!=", "<", "<=", ">", ">=", ":"];
+
+/// Name of the function the generated records support, returning the length of a list, a
+/// map or a string, and 0 for any other value.
+pub(crate) const LENGTH_FUNCTION: &str = "len";
+
+/// Generates the source of a filter, nesting operators up to the given depth.
+pub(crate) fn random_filter<R: Rng>(rng: &mut R, depth: usize) -> String {
+    if rng.random_ratio(1, 20) {
+        return String::new();
+    }
+
+    random_expression(rng, depth)
+}
+
+/// Generates a record holding a random subset of the fields filters refer to.
+pub(crate) fn random_record<R: Rng>(rng: &mut R) -> Value {
+    Value::Map(random_entries(rng, 2))
+}
+
+fn random_expression<R: Rng>(rng: &mut R, depth: usize) -> String {
+    if depth == 0 {
+        return random_restriction(rng, depth);
+    }
+
+    match rng.random_range(0..10) {
+        0..4 => random_restriction(rng, depth),
+        4..6 => random_operands(rng, depth, " AND "),
+        6..8 => random_operands(rng, depth, " OR "),
+        8 => random_operands(rng, depth, " "),
+        _ => format!("NOT {}", random_operand(rng, depth - 1)),
+    }
+}
+
+fn random_operands<R: Rng>(rng: &mut R, depth: usize, operator: &str) -> String {
+    let len = rng.random_range(2..=3);
+
+    (0..len)
+        .map(|_| random_operand(rng, depth - 1))
+        .collect::<Vec<_>>()
+        .join(operator)
+}
+
+/// Operand of an operator, parenthesized unless it is a restriction.
+fn random_operand<R: Rng>(rng: &mut R, depth: usize) -> String {
+    if depth == 0 || rng.random_bool(0.5) {
+        random_restriction(rng, depth)
+    } else {
+        format!("({})", random_expression(rng, depth))
+    }
+}
+
+fn random_restriction<R: Rng>(rng: &mut R, depth: usize) -> String {
+    let comparable = random_comparable(rng);
+    if rng.random_ratio(1, 5) {
+        return comparable;
+    }
+
+    let comparator = pick(COMPARATORS, rng);
+    let arg = match rng.random_range(0..10) {
+        0..6 => pick(LITERALS, rng).to_string(),
+        6 => random_path(rng),
+        7 => random_call(rng),
+        _ => format!("({})", random_composite(rng, depth.min(2))),
+    };
+
+    format!("{comparable} {comparator} {arg}")
+}
+
+/// Left-hand side of a comparison, mostly the path of a field.
+fn random_comparable<R: Rng>(rng: &mut R) -> String {
+    match rng.random_range(0..10) {
+        0..7 => random_path(rng),
+        7 | 8 => pick(LITERALS, rng).to_string(),
+        _ => random_call(rng),
+    }
+}
+
+fn random_path<R: Rng>(rng: &mut R) -> String {
+    let field = pick(FIELDS, rng);
+
+    if rng.random_ratio(1, 4) {
+        format!("{}.{field}", pick(FIELDS, rng))
+    } else {
+        field.to_string()
+    }
+}
+
+fn random_call<R: Rng>(rng: &mut R) -> String {
+    format!("{LENGTH_FUNCTION}({})", random_path(rng))
+}
+
+/// Composite argument over which a comparison is distributed, holding literals, and rarely
+/// comparisons which filters must be rejected for.
+fn random_composite<R: Rng>(rng: &mut R, depth: usize) -> String {
+    if rng.random_ratio(1, 50) {
+        let comparator = pick(COMPARATORS, rng);
+        return format!("{} {comparator} {}", random_path(rng), pick(LITERALS, rng));
+    }
+    if depth == 0 || rng.random_bool(0.4) {
+        return pick(LITERALS, rng).to_string();
+    }
+
+    match rng.random_range(0..3) {
+        0 => format!("NOT {}", random_composite_operand(rng, depth - 1)),
+        operator => {
+            let operator = if operator == 1 { " AND " } else { " OR " };
+            let len = rng.random_range(2..=3);
+
+            (0..len)
+                .map(|_| random_composite_operand(rng, depth - 1))
+                .collect::<Vec<_>>()
+                .join(operator)
+        }
+    }
+}
+
+/// Operand of an operator of a composite argument, parenthesized unless it is a literal.
+fn random_composite_operand<R: Rng>(rng: &mut R, depth: usize) -> String {
+    match random_composite(rng, depth) {
+        operand if operand.contains(' ') => format!("({operand})"),
+        operand => operand,
+    }
+}
+
+fn random_entries<R: Rng>(rng: &mut R, depth: usize) -> BTreeMap<String, Value> {
+    let mut entries = BTreeMap::new();
+    for field in FIELDS {
+        if rng.random_ratio(4, 5) {
+            entries.insert(field.to_string(), random_value(rng, depth));
+        }
+    }
+
+    entries
+}
+
+fn random_value<R: Rng>(rng: &mut R, depth: usize) -> Value {
+    match rng.random_range(0..20) {
+        18 if depth > 0 => {
+            let len = rng.random_range(0..=3);
+
+            Value::List((0..len).map(|_| random_value(rng, depth - 1)).collect())
+        }
+        19 if depth > 0 => Value::Map(random_entries(rng, depth - 1)),
+        _ => Value::Literal(random_literal(rng)),
+    }
+}
+
+fn random_literal<R: Rng>(rng: &mut R) -> Literal {
+    match rng.random_range(0..10) {
+        0 => Literal::Null,
+        1 => Literal::Boolean(rng.random()),
+        2 | 3 => Literal::Integer(rng.random_range(-1..=3)),
+        4 => Literal::Float(rng.random_range(-2..=6) as f32 * 0.5),
+        5 => Literal::Bytes(pick(STRINGS, rng).as_bytes().to_vec()),
+        6 => Literal::EnumValue(pick(&["ACTIVE", "PAUSED"], rng).to_string()),
+        _ => Literal::String(pick(STRINGS, rng).to_string()),
+    }
+}
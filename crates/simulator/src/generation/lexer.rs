@@ -1,46 +1,62 @@
-use super::{pick, random_string, readable_name_custom, Arbitrary};
-use crate::models::lexer::{Fragment, RawInput};
+use super::{
+    pick, pick_weighted, random_string, readable_name_custom, Arbitrary, Profile, UNICODE_WORDS,
+};
+use crate::models::lexer::{Fragment, InvalidInput, Mutation, MutationKind, RawInput};
 use rand::Rng;
 
 enum LiteralType {
     Boolean,
+    EscapedString,
     Float,
+    HexInteger,
     Integer,
     Null,
+    ScientificFloat,
+    SeparatedInteger,
     String,
+    UnicodeString,
 }
 
 enum FragmentKind {
     Field,
     Keyword,
     Literal,
+    Nesting,
     Operator,
     Other,
     Whitespace,
 }
 
 impl<'s> Arbitrary for Fragment<'s> {
-    fn arbitrary<R: Rng>(rng: &mut R) -> Self {
-        let ty = pick(
-            &[
-                FragmentKind::Field,
-                FragmentKind::Keyword,
-                FragmentKind::Literal,
-                FragmentKind::Operator,
-                FragmentKind::Other,
-                FragmentKind::Whitespace,
-            ],
-            rng,
-        );
+    fn arbitrary<R: Rng>(rng: &mut R, profile: &Profile) -> Self {
+        let weights = &profile.fragments;
+        let choices = [
+            (weights.field, FragmentKind::Field),
+            (weights.keyword, FragmentKind::Keyword),
+            (weights.literal, FragmentKind::Literal),
+            (weights.nesting, FragmentKind::Nesting),
+            (weights.operator, FragmentKind::Operator),
+            (weights.other, FragmentKind::Other),
+            (weights.whitespace, FragmentKind::Whitespace),
+        ];
+
+        match pick_weighted(&choices, rng) {
+            FragmentKind::Field => random_field(rng, profile),
+            FragmentKind::Keyword => Self::new(pick::<&str, _>(&["AND", "OR", "NOT"], rng)),
+            FragmentKind::Literal => random_literal(rng, profile),
+            FragmentKind::Nesting => {
+                let depth = rng.random_range(profile.nesting.depth.range());
+                let parenthesis = pick(&["(", ")"], rng);
 
-        match ty {
-            FragmentKind::Field => random_field(rng),
-            FragmentKind::Keyword => Self::new(*pick(&["AND", "OR", "NOT"], rng)),
-            FragmentKind::Literal => random_literal(rng),
+                Self::new(&parenthesis.repeat(depth))
+            }
             FragmentKind::Operator => random_operator(rng),
-            FragmentKind::Other => Self::new(*pick(&["(", ")", ",", "."], rng)),
+            FragmentKind::Other => Self::new(pick::<&str, _>(&["(", ")", ",", "."], rng)),
             FragmentKind::Whitespace => {
-                let whitespace = vec![*pick(&[0x9, 0xa, 0xc, b'\n', b' '], rng)];
+                let size = rng.random_range(profile.whitespace.run.range());
+                let whitespace = (0..size)
+                    .map(|_| *pick(&[0x9, 0xa, 0xc, b'\n', b' '], rng))
+                    .collect::<Vec<_>>();
                 let whitespace =
                     String::from_utf8(whitespace).expect("unable to generate valid whitespace");
 
@@ -51,51 +67,189 @@ impl<'s> Arbitrary for Fragment<'s> {
 }
 
 impl<'i> Arbitrary for RawInput<'i> {
-    fn arbitrary<R: Rng>(rng: &mut R) -> Self {
-        let size = rng.random_range(64..2 * 1024);
+    fn arbitrary<R: Rng>(rng: &mut R, profile: &Profile) -> Self {
+        let size = rng.random_range(profile.input.size.range());
         let mut fragments = Vec::with_capacity(size);
 
         for _ in 0..size {
-            fragments.push(Fragment::arbitrary(rng));
+            fragments.push(Fragment::arbitrary(rng, profile));
         }
 
         Self::new(fragments)
     }
 }
 
+impl<'i> Arbitrary for InvalidInput<'i> {
+    fn arbitrary<R: Rng>(rng: &mut R, profile: &Profile) -> Self {
+        let prefix = RawInput::arbitrary(rng, profile);
+        let size = rng.random_range(0..=prefix.fragments().len());
+        let prefix = RawInput::new(prefix.fragments()[..size].to_vec());
+
+        Self::new(prefix, Mutation::arbitrary(rng, profile))
+    }
+}
+
+impl<'s> Arbitrary for Mutation<'s> {
+    fn arbitrary<R: Rng>(rng: &mut R, profile: &Profile) -> Self {
+        let weights = &profile.mutations;
+        let kind = *pick_weighted(
+            &[
+                (weights.truncated_string, MutationKind::TruncatedString),
+                (weights.stray_underscore, MutationKind::StrayUnderscore),
+                (weights.empty_hex_number, MutationKind::EmptyHexNumber),
+                (weights.dangling_exponent, MutationKind::DanglingExponent),
+                (weights.lone_bang, MutationKind::LoneBang),
+            ],
+            rng,
+        );
+
+        let fragment = match kind {
+            MutationKind::TruncatedString => {
+                let string = random_string(rng, profile);
+
+                Fragment::new(&string[..string.len() - 1])
+            }
+            MutationKind::StrayUnderscore => {
+                let digits = rng.random_range(1..=i64::MAX).to_string();
+                let idx = rng.random_range(1..=digits.len());
+
+                // A single `_` between two digits is a valid separator, so it has to be
+                // doubled unless it ends the number
+                let underscore = if idx == digits.len() { "_" } else { "__" };
+
+                Fragment::new(&format!("{}{underscore}{}", &digits[..idx], &digits[idx..]))
+            }
+            MutationKind::EmptyHexNumber => Fragment::new(pick::<&str, _>(&["0x", "0X"], rng)),
+            MutationKind::DanglingExponent => {
+                let mantissa = if rng.random() {
+                    rng.random_range(0..1024).to_string()
+                } else {
+                    rng.random::<f32>().to_string()
+                };
+                let exponent = pick(&["e+", "e-", "E+", "E-"], rng);
+
+                Fragment::new(&format!("{mantissa}{exponent}"))
+            }
+            MutationKind::LoneBang => Fragment::new("!"),
+        };
+
+        Self { kind, fragment }
+    }
+}
+
 #[inline(always)]
-fn random_field<'s, R: Rng>(rng: &mut R) -> Fragment<'s> {
-    Fragment::new(&readable_name_custom("_", rng))
+fn random_field<'s, R: Rng>(rng: &mut R, profile: &Profile) -> Fragment<'s> {
+    let size = rng.random_range(profile.identifiers.words.range());
+    let words = if rng.random_bool(profile.identifiers.unicode_ratio) {
+        (0..size)
+            .map(|_| (*pick(UNICODE_WORDS, rng)).to_owned())
+            .collect::<Vec<_>>()
+    } else {
+        (0..size)
+            .map(|_| readable_name_custom("_", &mut *rng))
+            .collect::<Vec<_>>()
+    };
+
+    Fragment::new(&words.join("_"))
 }
 
 #[inline(always)]
-fn random_literal<'s, R: Rng>(rng: &mut R) -> Fragment<'s> {
-    let ty = pick(
-        &[
-            LiteralType::Boolean,
-            LiteralType::Float,
-            LiteralType::Integer,
-            LiteralType::Null,
-            LiteralType::String,
-        ],
-        rng,
-    );
+fn random_literal<'s, R: Rng>(rng: &mut R, profile: &Profile) -> Fragment<'s> {
+    let weights = &profile.literals;
+    let choices = [
+        (weights.boolean, LiteralType::Boolean),
+        (weights.escaped_string, LiteralType::EscapedString),
+        (weights.float, LiteralType::Float),
+        (weights.hex_integer, LiteralType::HexInteger),
+        (weights.integer, LiteralType::Integer),
+        (weights.null, LiteralType::Null),
+        (weights.scientific_float, LiteralType::ScientificFloat),
+        (weights.separated_integer, LiteralType::SeparatedInteger),
+        (weights.string, LiteralType::String),
+        (weights.unicode_string, LiteralType::UnicodeString),
+    ];
+
+    match pick_weighted(&choices, rng) {
+        LiteralType::Boolean => Fragment::new(pick::<&str, _>(&["true", "false"], rng)),
+        LiteralType::EscapedString => {
+            let string = random_string(rng, profile);
+            let content = &string[1..string.len() - 1];
+            let idx = rng.random_range(0..=content.len());
 
-    match ty {
-        LiteralType::Boolean => Fragment::new(*pick(&["true", "false"], rng)),
+            Fragment::new(&format!("\"{}\"\"{}\"", &content[..idx], &content[idx..]))
+        }
         LiteralType::Float => {
             let value = rng.random::<f32>();
             let value = if rng.random() { -value } else { value };
 
             Fragment::new(&value.to_string())
         }
+        // Hexadecimal literals are surrounded by whitespaces: a digit generated right
+        // before them would turn their `0x` prefix into the end of a decimal number, and an
+        // identifier generated right after them would be scanned as part of the number
+        LiteralType::HexInteger => {
+            let value = rng.random_range(0..=i64::MAX);
+            let literal = match rng.random_range(0..4) {
+                0 => format!(" 0x{value:x} "),
+                1 => format!(" 0x{value:X} "),
+                2 => format!(" 0X{value:x} "),
+                _ => format!(" 0X{value:X} "),
+            };
+
+            Fragment::new(&literal)
+        }
         LiteralType::Integer => Fragment::new(&rng.random::<i64>().to_string()),
         LiteralType::Null => Fragment::new("null"),
-        LiteralType::String => Fragment::new(&random_string(rng)),
+        // Same goes for identifiers generated after scientific literals
+        LiteralType::ScientificFloat => {
+            let sign = if rng.random() { "-" } else { "" };
+            let mantissa = if rng.random() {
+                rng.random_range(1..1000).to_string()
+            } else {
+                format!(
+                    "{}.{}",
+                    rng.random_range(0..10),
+                    rng.random_range(0..100_000)
+                )
+            };
+            let exponent = pick(&["e", "E"], rng);
+            let exponent_sign = pick(&["", "+", "-"], rng);
+            let exponent_value = rng.random_range(0..=38);
+
+            Fragment::new(&format!(
+                "{sign}{mantissa}{exponent}{exponent_sign}{exponent_value} "
+            ))
+        }
+        LiteralType::SeparatedInteger => {
+            let digits = rng.random_range(1..=i64::MAX).to_string();
+            let mut literal = String::with_capacity(digits.len() * 2);
+
+            for (idx, digit) in digits.chars().enumerate() {
+                if idx > 0 && rng.random_ratio(1, 3) {
+                    literal.push('_');
+                }
+
+                literal.push(digit);
+            }
+
+            Fragment::new(&literal)
+        }
+        LiteralType::String => Fragment::new(&random_string(rng, profile)),
+        LiteralType::UnicodeString => {
+            let size = rng.random_range(1..=8);
+            let words = (0..size)
+                .map(|_| *pick(UNICODE_WORDS, rng))
+                .collect::<Vec<_>>();
+
+            Fragment::new(&format!("\"{}\"", words.join(" ")))
+        }
     }
 }
 
 #[inline(always)]
 fn random_operator<'s, R: Rng>(rng: &mut R) -> Fragment<'s> {
-    Fragment::new(*pick(&[":", "-", "=", "!=", ">", ">=", "<", "<="], rng))
+    Fragment::new(pick::<&str, _>(
+        &[":", "-", "=", "!=", ">", ">=", "<", "<="],
+        rng,
+    ))
 }
@@ -1,14 +1,18 @@
 use anarchist_readable_name_generator_lib::readable_name_custom;
 use rand::Rng;
 
+pub(crate) use profile::Profile;
+
+pub mod filter;
 pub mod lexer;
+pub mod profile;
 
 pub trait Arbitrary {
-    fn arbitrary<R: Rng>(rng: &mut R) -> Self;
+    fn arbitrary<R: Rng>(rng: &mut R, profile: &Profile) -> Self;
 }
 
-pub(crate) fn random_string<R: Rng>(rng: &mut R) -> String {
-    let size = rng.random_range(2..=256);
+pub(crate) fn random_string<R: Rng>(rng: &mut R, profile: &Profile) -> String {
+    let size = rng.random_range(profile.strings.size.range());
     let mut output = "\"".to_owned();
 
     for _ in 0..size {
@@ -26,6 +30,20 @@ pub(crate) fn random_string<R: Rng>(rng: &mut R) -> String {
     output
 }
 
+/// Unicode words used to exercise non-ASCII identifier and string bytes.
+pub(crate) const UNICODE_WORDS: &[&str] = &[
+    "café",
+    "naïve",
+    "straße",
+    "Ωmega",
+    "東京",
+    "Москва",
+    "ñandú",
+    "crème_brûlée",
+    "λ",
+    "🦀",
+];
+
 pub(crate) fn pick<'a, T, R>(choices: &'a [T], rng: &mut R) -> &'a T
 where
     R: Rng,
@@ -34,3 +52,22 @@ where
 
     &choices[idx]
 }
+
+/// Picks one of the `choices`, each one being picked proportionally to its weight.
+pub(crate) fn pick_weighted<'a, T, R>(choices: &'a [(u32, T)], rng: &mut R) -> &'a T
+where
+    R: Rng,
+{
+    let total = choices.iter().map(|(weight, _)| weight).sum::<u32>();
+    let mut target = rng.random_range(0..total);
+
+    for (weight, choice) in choices {
+        if target < *weight {
+            return choice;
+        }
+
+        target -= weight;
+    }
+
+    unreachable!("weighted pick target is always lower than the total weight")
+}
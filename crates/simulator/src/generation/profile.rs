@@ -0,0 +1,383 @@
+use serde::Deserialize;
+use std::{ops::RangeInclusive, path::Path};
+
+/// Knobs steering the simulator's generators.
+///
+/// Profiles are loaded from TOML files, or JSON ones when their extension is `.json`. Every
+/// field is optional and defaults to the simulator's regular behaviour, so a profile only
+/// needs to list what it changes, e.g.:
+///
+/// ```toml
+/// [input]
+/// size = { min = 16, max = 64 }
+///
+/// [fragments]
+/// nesting = 4
+/// whitespace = 3
+///
+/// [whitespace]
+/// run = { min = 8, max = 128 }
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Profile {
+    pub(crate) input: InputProfile,
+    pub(crate) fragments: FragmentWeights,
+    pub(crate) literals: LiteralWeights,
+    pub(crate) mutations: MutationWeights,
+    pub(crate) identifiers: IdentifierProfile,
+    pub(crate) strings: StringProfile,
+    pub(crate) whitespace: WhitespaceProfile,
+    pub(crate) nesting: NestingProfile,
+}
+
+impl Profile {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+
+        let profile: Self = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|err| err.to_string())?
+        } else {
+            toml::from_str(&content).map_err(|err| err.to_string())?
+        };
+        profile.validate()?;
+
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.input.size.validate("input.size")?;
+        self.identifiers.words.validate("identifiers.words")?;
+        self.strings.size.validate("strings.size")?;
+        self.whitespace.run.validate("whitespace.run")?;
+        self.nesting.depth.validate("nesting.depth")?;
+
+        if self.identifiers.words.min == 0 {
+            return Err("identifiers.words.min must be positive".to_owned());
+        }
+
+        if !(0.0..=1.0).contains(&self.identifiers.unicode_ratio) {
+            return Err("identifiers.unicode_ratio must be between 0 and 1".to_owned());
+        }
+
+        for (name, total) in [
+            ("fragments", self.fragments.total()),
+            ("literals", self.literals.total()),
+            ("mutations", self.mutations.total()),
+        ] {
+            match total {
+                None => {
+                    return Err(format!(
+                        "the {name} weights must not sum above {}",
+                        u32::MAX
+                    ));
+                }
+                Some(0) => {
+                    return Err(format!(
+                        "at least one of the {name} weights must be positive"
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Inclusive bounds of a randomly picked size.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Bounds {
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+}
+
+impl Bounds {
+    #[inline(always)]
+    pub(crate) const fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+
+    #[inline(always)]
+    pub(crate) fn range(&self) -> RangeInclusive<usize> {
+        self.min..=self.max
+    }
+
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if self.min > self.max {
+            return Err(format!("{name}.min must not be greater than {name}.max"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Sum of weights, `None` when it overflows.
+#[inline]
+fn total(weights: &[u32]) -> Option<u32> {
+    weights
+        .iter()
+        .try_fold(0u32, |total, weight| total.checked_add(*weight))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct InputProfile {
+    /// Number of fragments of a generated input
+    pub(crate) size: Bounds,
+}
+
+impl Default for InputProfile {
+    fn default() -> Self {
+        Self {
+            size: Bounds::new(64, 2 * 1024 - 1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct FragmentWeights {
+    pub(crate) field: u32,
+    pub(crate) keyword: u32,
+    pub(crate) literal: u32,
+    pub(crate) nesting: u32,
+    pub(crate) operator: u32,
+    pub(crate) other: u32,
+    pub(crate) whitespace: u32,
+}
+
+impl FragmentWeights {
+    fn total(&self) -> Option<u32> {
+        total(&[
+            self.field,
+            self.keyword,
+            self.literal,
+            self.nesting,
+            self.operator,
+            self.other,
+            self.whitespace,
+        ])
+    }
+}
+
+impl Default for FragmentWeights {
+    fn default() -> Self {
+        Self {
+            field: 1,
+            keyword: 1,
+            literal: 1,
+            nesting: 0,
+            operator: 1,
+            other: 1,
+            whitespace: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct LiteralWeights {
+    pub(crate) boolean: u32,
+    pub(crate) escaped_string: u32,
+    pub(crate) float: u32,
+    pub(crate) hex_integer: u32,
+    pub(crate) integer: u32,
+    pub(crate) null: u32,
+    pub(crate) scientific_float: u32,
+    pub(crate) separated_integer: u32,
+    pub(crate) string: u32,
+    pub(crate) unicode_string: u32,
+}
+
+impl LiteralWeights {
+    fn total(&self) -> Option<u32> {
+        total(&[
+            self.boolean,
+            self.escaped_string,
+            self.float,
+            self.hex_integer,
+            self.integer,
+            self.null,
+            self.scientific_float,
+            self.separated_integer,
+            self.string,
+            self.unicode_string,
+        ])
+    }
+}
+
+impl Default for LiteralWeights {
+    fn default() -> Self {
+        Self {
+            boolean: 2,
+            escaped_string: 2,
+            float: 3,
+            hex_integer: 2,
+            integer: 3,
+            null: 1,
+            scientific_float: 2,
+            separated_integer: 2,
+            string: 3,
+            unicode_string: 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct MutationWeights {
+    pub(crate) truncated_string: u32,
+    pub(crate) stray_underscore: u32,
+    pub(crate) empty_hex_number: u32,
+    pub(crate) dangling_exponent: u32,
+    pub(crate) lone_bang: u32,
+}
+
+impl MutationWeights {
+    fn total(&self) -> Option<u32> {
+        total(&[
+            self.truncated_string,
+            self.stray_underscore,
+            self.empty_hex_number,
+            self.dangling_exponent,
+            self.lone_bang,
+        ])
+    }
+}
+
+impl Default for MutationWeights {
+    fn default() -> Self {
+        Self {
+            truncated_string: 1,
+            stray_underscore: 1,
+            empty_hex_number: 1,
+            dangling_exponent: 1,
+            lone_bang: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct IdentifierProfile {
+    /// Probability for an identifier to be made of unicode words
+    pub(crate) unicode_ratio: f64,
+
+    /// Number of words joined by `_` into a single identifier
+    pub(crate) words: Bounds,
+}
+
+impl Default for IdentifierProfile {
+    fn default() -> Self {
+        Self {
+            unicode_ratio: 0.2,
+            words: Bounds::new(1, 1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct StringProfile {
+    /// Number of characters between the quotes of a string literal
+    pub(crate) size: Bounds,
+}
+
+impl Default for StringProfile {
+    fn default() -> Self {
+        Self {
+            size: Bounds::new(2, 256),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct WhitespaceProfile {
+    /// Number of whitespace bytes of a whitespace fragment
+    pub(crate) run: Bounds,
+}
+
+impl Default for WhitespaceProfile {
+    fn default() -> Self {
+        Self {
+            run: Bounds::new(1, 1),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct NestingProfile {
+    /// Number of parentheses opened, or closed, by a nesting fragment
+    pub(crate) depth: Bounds,
+}
+
+impl Default for NestingProfile {
+    fn default() -> Self {
+        Self {
+            depth: Bounds::new(1, 32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn it_parses_a_partial_profile() {
+        let profile = toml::from_str::<Profile>(
+            "[input]\nsize = { min = 16, max = 64 }\n\n[fragments]\nnesting = 4\n",
+        )
+        .unwrap();
+
+        assert!(profile.validate().is_ok());
+        assert_eq!(profile.input.size.range(), 16..=64);
+        assert_eq!(profile.fragments.nesting, 4);
+        // Fields the profile does not list keep their defaults
+        assert_eq!(profile.fragments.field, 1);
+        assert_eq!(profile.strings.size.range(), 2..=256);
+    }
+
+    #[test]
+    fn it_parses_a_json_profile() {
+        let profile = serde_json::from_str::<Profile>(r#"{ "literals": { "null": 7 } }"#).unwrap();
+
+        assert!(profile.validate().is_ok());
+        assert_eq!(profile.literals.null, 7);
+    }
+
+    #[rstest]
+    #[case::unknown_field("[input]\nsise = { min = 1, max = 2 }\n")]
+    #[case::unknown_section("[inputs]\n")]
+    fn it_rejects_unknown_fields(#[case] content: &str) {
+        assert!(toml::from_str::<Profile>(content).is_err());
+    }
+
+    #[rstest]
+    #[case::bounds("[input]\nsize = { min = 8, max = 4 }\n", "input.size.min")]
+    #[case::words(
+        "[identifiers]\nwords = { min = 0, max = 1 }\n",
+        "identifiers.words.min"
+    )]
+    #[case::ratio("[identifiers]\nunicode_ratio = 1.5\n", "unicode_ratio")]
+    #[case::zero_weights(
+        "[mutations]\ntruncated_string = 0\nstray_underscore = 0\nempty_hex_number = 0\n\
+         dangling_exponent = 0\nlone_bang = 0\n",
+        "mutations weights must be positive"
+    )]
+    #[case::overflowing_weights(
+        "[literals]\nboolean = 4294967295\nnull = 1\n",
+        "literals weights must not sum above"
+    )]
+    fn it_rejects_an_invalid_profile(#[case] content: &str, #[case] expected: &str) {
+        let profile = toml::from_str::<Profile>(content).unwrap();
+        let err = profile.validate().unwrap_err();
+
+        assert!(err.contains(expected), "{err}");
+    }
+}
@@ -5,8 +5,10 @@ use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Env
 
 mod cli;
 mod commands;
+mod error;
 mod generation;
 mod models;
+mod shrinking;
 
 fn main() -> ExitCode {
     tracing_subscriber::registry()
@@ -24,6 +26,10 @@ fn main() -> ExitCode {
     tracing::info!(seed = seed, "starting simulation");
 
     std::panic::set_hook(Box::new(move |info| {
+        if shrinking::is_shrinking() {
+            return;
+        }
+
         let payload = info.payload();
         if let Some(message) = payload.downcast_ref::<String>() {
             tracing::error!(message = message, "panic caught during simulation");
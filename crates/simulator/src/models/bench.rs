@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable report of a lexer benchmark, which can be saved to serve as the
+/// baseline of later runs.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct BenchReport {
+    pub(crate) seed: u64,
+    pub(crate) inputs: usize,
+    pub(crate) bytes: u64,
+    pub(crate) tokens: u64,
+    pub(crate) iterations: u64,
+    pub(crate) tokens_per_sec: Percentiles,
+    pub(crate) bytes_per_sec: Percentiles,
+}
+
+/// Distribution of a throughput measured once per benchmark iteration.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(crate) struct Percentiles {
+    pub(crate) min: f64,
+    pub(crate) p50: f64,
+    pub(crate) p90: f64,
+    pub(crate) p99: f64,
+    pub(crate) max: f64,
+}
+
+impl Percentiles {
+    /// Computes the percentiles of `samples`, which must not be empty.
+    pub(crate) fn of(samples: &mut [f64]) -> Self {
+        samples.sort_by(f64::total_cmp);
+
+        let at = |percentile: usize| {
+            let idx = (samples.len() * percentile).div_ceil(100).max(1) - 1;
+
+            samples[idx]
+        };
+
+        Self {
+            min: samples[0],
+            p50: at(50),
+            p90: at(90),
+            p99: at(99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::plan::Expectation;
 use std::{borrow::Cow, fmt, ops::Deref};
 
 #[derive(Clone, Debug)]
@@ -37,6 +38,11 @@ impl<'i> RawInput<'i> {
         Self { fragments }
     }
 
+    #[inline(always)]
+    pub(crate) fn fragments(&self) -> &[Fragment<'i>] {
+        &self.fragments
+    }
+
     pub(crate) fn as_bytes(&self) -> Vec<u8> {
         let mut output = Vec::with_capacity(self.fragments.len());
         for fragment in &self.fragments {
@@ -52,3 +58,94 @@ impl fmt::Display for RawInput<'_> {
         write!(f, "{}", String::from_utf8_lossy(&self.as_bytes()))
     }
 }
+
+/// Corruption applied to a fragment to turn it into an invalid one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum MutationKind {
+    /// `"abc` with no closing quote
+    TruncatedString,
+
+    /// `1__2` or `12_`
+    StrayUnderscore,
+
+    /// `0x` with no digits
+    EmptyHexNumber,
+
+    /// `1e+` with no exponent digits
+    DanglingExponent,
+
+    /// `!` not followed by `=`
+    LoneBang,
+}
+
+impl MutationKind {
+    /// Name of the `rapiere_lexer::Error` variant the mutation should be reported with.
+    pub(crate) fn expected_error(&self) -> &'static str {
+        match self {
+            Self::TruncatedString => "UnterminatedStringLiteral",
+            Self::StrayUnderscore | Self::DanglingExponent => "BadNumber",
+            Self::EmptyHexNumber => "MalformatedHexNumber",
+            Self::LoneBang => "UnrecognizedToken",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Mutation<'s> {
+    pub(crate) kind: MutationKind,
+    pub(crate) fragment: Fragment<'s>,
+}
+
+/// A valid input followed by a corrupted fragment.
+///
+/// The lexer stops at the first error, so the corrupted fragment always ends the input and
+/// is separated from the valid prefix by a whitespace to keep it from merging with the
+/// prefix's last token.
+#[derive(Clone, Debug)]
+pub(crate) struct InvalidInput<'i> {
+    pub(crate) prefix: RawInput<'i>,
+    pub(crate) mutation: Mutation<'i>,
+}
+
+impl<'i> InvalidInput<'i> {
+    #[inline(always)]
+    pub(crate) fn new(prefix: RawInput<'i>, mutation: Mutation<'i>) -> Self {
+        Self { prefix, mutation }
+    }
+
+    pub(crate) fn as_bytes(&self) -> Vec<u8> {
+        let mut output = self.prefix.as_bytes();
+        output.push(b' ');
+        output.extend(self.mutation.fragment.deref());
+
+        output
+    }
+
+    /// Returns the error the lexer must report, positioned on the corrupted fragment's
+    /// first byte.
+    pub(crate) fn expectation(&self) -> Expectation {
+        let mut line = 1;
+        let mut column = 1;
+
+        for b in self.prefix.as_bytes().iter().chain(b" ") {
+            if *b == b'\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        Expectation::Error {
+            kind: self.mutation.kind.expected_error().to_owned(),
+            line: Some(line),
+            column: Some(column),
+        }
+    }
+}
+
+impl fmt::Display for InvalidInput<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.as_bytes()))
+    }
+}
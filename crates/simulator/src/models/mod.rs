@@ -1 +1,4 @@
+pub mod bench;
 pub mod lexer;
+pub mod plan;
+pub mod report;
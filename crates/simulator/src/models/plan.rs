@@ -0,0 +1,144 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Component of the `rapiere` ecosystem a simulation plan is fed to.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Component {
+    #[default]
+    Lexer,
+}
+
+/// A structured simulation plan, carrying the expected outcome along with the input.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Plan {
+    #[serde(default)]
+    pub(crate) component: Component,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) seed: Option<u64>,
+
+    pub(crate) input: String,
+
+    #[serde(default)]
+    pub(crate) expect: Expectation,
+}
+
+/// Outcome expected when replaying a plan.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "outcome")]
+pub(crate) enum Expectation {
+    #[default]
+    Success,
+
+    Error {
+        kind: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        line: Option<u64>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        column: Option<u64>,
+    },
+
+    Panic,
+}
+
+impl Expectation {
+    pub(crate) fn matches(&self, outcome: &Outcome) -> bool {
+        match (self, outcome) {
+            (Self::Success, Outcome::Success) | (Self::Panic, Outcome::Panic) => true,
+            (
+                Self::Error { kind, line, column },
+                Outcome::Error {
+                    kind: actual_kind,
+                    line: actual_line,
+                    column: actual_column,
+                },
+            ) => {
+                kind == actual_kind
+                    && line.is_none_or(|line| Some(line) == *actual_line)
+                    && column.is_none_or(|column| Some(column) == *actual_column)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "success"),
+            Self::Error { kind, line, column } => fmt_error(f, kind, *line, *column),
+            Self::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+/// Outcome observed when replaying a plan.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Outcome {
+    Success,
+
+    Error {
+        kind: &'static str,
+        line: Option<u64>,
+        column: Option<u64>,
+    },
+
+    Panic,
+}
+
+impl Outcome {
+    /// Returns whether both outcomes are of the same kind, regardless of error positions.
+    pub(crate) fn same_kind(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Success, Self::Success) | (Self::Panic, Self::Panic) => true,
+            (Self::Error { kind, .. }, Self::Error { kind: other, .. }) => kind == other,
+            _ => false,
+        }
+    }
+}
+
+impl From<&rapiere_lexer::Error> for Outcome {
+    fn from(err: &rapiere_lexer::Error) -> Self {
+        let kind = match err {
+            rapiere_lexer::Error::BadNumber(_) => "BadNumber",
+            rapiere_lexer::Error::MalformatedHexNumber(_) => "MalformatedHexNumber",
+            rapiere_lexer::Error::UnrecognizedToken(_) => "UnrecognizedToken",
+            rapiere_lexer::Error::UnterminatedStringLiteral(_) => "UnterminatedStringLiteral",
+        };
+        let (line, column) = err.position();
+
+        Self::Error { kind, line, column }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Success => write!(f, "success"),
+            Self::Error { kind, line, column } => fmt_error(f, kind, *line, *column),
+            Self::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+fn fmt_error(
+    f: &mut fmt::Formatter<'_>,
+    kind: &str,
+    line: Option<u64>,
+    column: Option<u64>,
+) -> fmt::Result {
+    write!(f, "{kind} error")?;
+    if let Some(line) = line {
+        write!(f, " at line {line}")?;
+    }
+    if let Some(column) = column {
+        write!(f, " column {column}")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use super::plan::Component;
+use serde::Serialize;
+
+/// Machine-readable report of a simulation campaign.
+#[derive(Debug, Serialize)]
+pub(crate) struct CampaignReport {
+    pub(crate) component: Component,
+    pub(crate) seed: u64,
+    pub(crate) runs: u64,
+    pub(crate) jobs: usize,
+    pub(crate) passed: u64,
+    pub(crate) failed: u64,
+    pub(crate) duration_ms: u128,
+    pub(crate) failures: Vec<Failure>,
+}
+
+/// A failing run of a simulation campaign.
+#[derive(Debug, Serialize)]
+pub(crate) struct Failure {
+    pub(crate) seed: u64,
+    pub(crate) outcome: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+
+    pub(crate) reproducer: String,
+}
@@ -0,0 +1,67 @@
+use super::{ddmin, Shrink};
+use crate::models::lexer::{Fragment, InvalidInput, RawInput};
+
+impl<'i> Shrink for RawInput<'i> {
+    fn shrink<F: FnMut(&Self) -> bool>(&self, mut fails: F) -> Self {
+        let fragments = ddmin(self.fragments().to_vec(), |fragments| {
+            fails(&RawInput::new(fragments.to_vec()))
+        });
+        let mut input = RawInput::new(fragments);
+
+        // Simplifying a fragment may allow another one to be simplified in turn, so we
+        // loop until a whole pass leaves the input untouched.
+        let mut simplified = true;
+        while simplified {
+            simplified = false;
+
+            for idx in 0..input.fragments().len() {
+                for candidate in simplifications(&input.fragments()[idx]) {
+                    let mut fragments = input.fragments().to_vec();
+                    fragments[idx] = candidate;
+
+                    let candidate = RawInput::new(fragments);
+                    if fails(&candidate) {
+                        input = candidate;
+                        simplified = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        input
+    }
+}
+
+impl<'i> Shrink for InvalidInput<'i> {
+    fn shrink<F: FnMut(&Self) -> bool>(&self, mut fails: F) -> Self {
+        let prefix = self.prefix.shrink(|prefix| {
+            fails(&InvalidInput::new(prefix.clone(), self.mutation.clone()))
+        });
+
+        InvalidInput::new(prefix, self.mutation.clone())
+    }
+}
+
+/// Returns simpler versions of a fragment, from the simplest to the most complex one.
+///
+/// Only candidates shorter than the fragment, or as long but lexicographically lower,
+/// are returned so that shrinking always terminates.
+fn simplifications<'s>(fragment: &Fragment<'_>) -> Vec<Fragment<'s>> {
+    let candidates: &[&str] = match fragment.first() {
+        Some(b'"') => &["\"\"", "\"a\""],
+        Some(b'-' | b'.' | b'0'..=b'9') if fragment.len() > 1 => &["0", "1", "-1", "0.5"],
+        Some(b) if b.is_ascii_whitespace() => &[" "],
+        Some(b) if b.is_ascii_alphabetic() || *b > b'\x7f' || *b == b'_' => &["a"],
+        _ => &[],
+    };
+
+    candidates
+        .iter()
+        .filter(|candidate| {
+            candidate.len() < fragment.len()
+                || (candidate.len() == fragment.len() && candidate.as_bytes() < &fragment[..])
+        })
+        .map(|candidate| Fragment::new(candidate))
+        .collect()
+}
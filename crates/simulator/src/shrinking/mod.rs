@@ -0,0 +1,118 @@
+use std::cell::Cell;
+
+pub mod lexer;
+
+thread_local! {
+    static SHRINKING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub trait Shrink: Sized {
+    /// Reduces `self` to a smaller value for which `fails` still returns `true`.
+    fn shrink<F: FnMut(&Self) -> bool>(&self, fails: F) -> Self;
+}
+
+/// Returns whether the current thread is shrinking a failing input.
+///
+/// Candidates tried while shrinking are expected to panic, so the panic hook uses this
+/// to keep them out of the simulation logs.
+#[inline(always)]
+pub(crate) fn is_shrinking() -> bool {
+    SHRINKING.with(Cell::get)
+}
+
+/// Runs `f` with the current thread flagged as shrinking.
+pub(crate) fn shrinking<T>(f: impl FnOnce() -> T) -> T {
+    struct Guard(bool);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            SHRINKING.with(|flag| flag.set(self.0));
+        }
+    }
+
+    let _guard = Guard(SHRINKING.with(|flag| flag.replace(true)));
+
+    f()
+}
+
+/// Zeller's delta debugging algorithm (`ddmin`).
+///
+/// Returns a subsequence of `items` which still fails and from which no single chunk,
+/// at the finest granularity reached, can be removed without the failure vanishing.
+pub(crate) fn ddmin<T, F>(items: Vec<T>, mut fails: F) -> Vec<T>
+where
+    T: Clone,
+    F: FnMut(&[T]) -> bool,
+{
+    let mut items = items;
+    let mut granularity = 2;
+
+    while items.len() >= 2 {
+        let chunk_size = items.len().div_ceil(granularity);
+        let chunks = items
+            .chunks(chunk_size)
+            .map(<[T]>::to_vec)
+            .collect::<Vec<_>>();
+
+        if let Some(chunk) = chunks.iter().find(|chunk| fails(chunk)) {
+            items = chunk.clone();
+            granularity = 2;
+            continue;
+        }
+
+        let complement = (0..chunks.len()).find_map(|skipped| {
+            let complement = chunks
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| *idx != skipped)
+                .flat_map(|(_, chunk)| chunk.iter().cloned())
+                .collect::<Vec<_>>();
+
+            fails(&complement).then_some(complement)
+        });
+
+        if let Some(complement) = complement {
+            items = complement;
+            granularity = (granularity - 1).max(2);
+            continue;
+        }
+
+        if granularity >= items.len() {
+            break;
+        }
+
+        granularity = (granularity * 2).min(items.len());
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::single(&[42], vec![42])]
+    #[case::pair(&[3, 42], vec![3, 42])]
+    #[case::scattered(&[0, 17, 63, 99], vec![0, 17, 63, 99])]
+    fn it_shrinks_to_the_failing_items(#[case] culprits: &[u32], #[case] expected: Vec<u32>) {
+        let fails = |items: &[u32]| culprits.iter().all(|culprit| items.contains(culprit));
+
+        assert_eq!(ddmin((0..100).collect(), fails), expected);
+    }
+
+    #[test]
+    fn it_keeps_an_input_which_cannot_be_shrunk() {
+        let items = vec![1, 2, 3];
+
+        assert_eq!(ddmin(items.clone(), |items| items.len() == 3), items);
+    }
+
+    #[test]
+    fn it_flags_the_thread_while_shrinking() {
+        assert!(!is_shrinking());
+        assert!(shrinking(|| shrinking(is_shrinking)));
+        assert!(!is_shrinking());
+    }
+}